@@ -1,26 +1,47 @@
-/// Running camera on a seperate thread and returning the frames
-use crossbeam_channel::Sender;
+/// Running camera (or any other frame source) on a seperate thread and returning the frames
+use crossbeam_channel::{unbounded, Sender};
 
 use opencv::{
-    prelude::{Mat, VideoCaptureTrait, VideoCaptureTraitConst},
-    videoio,
+    core::{Rect, Size},
+    prelude::Mat,
+    videoio::{self, VideoCaptureAPIs},
 };
 use std::{
     sync::{
         self,
         atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
     },
     thread,
+    time::{Duration, Instant},
 };
 
 use std::collections::HashMap;
 
-use crate::structs::camera::ThreadedCamera;
+use crate::{
+    consts::{CAMERA_API, MIN_CAPTURE_ROI_SIZE, RECONNECT_MAX_BACKOFF, RECONNECT_MIN_BACKOFF},
+    enums::{
+        camera_control::CameraControl, camera_event::CameraEvent, camera_status::CameraStatus,
+        capture_backend::CaptureBackend,
+    },
+    source::FrameSource,
+    structs::{
+        camera::{CameraControls, CameraDevice, CaptureFormat, CaptureRoi, ThreadedCamera},
+        frame::Frame,
+        mailbox::FrameMailbox,
+    },
+};
+use nokhwa::{
+    pixel_format::RgbFormat,
+    utils::{CameraIndex, FrameFormat, RequestedFormat, RequestedFormatType},
+    Camera,
+};
 
 use anyhow::Result;
 
 impl ThreadedCamera {
-    pub fn get_available_cameras() -> Result<HashMap<String, i32>> {
+    // Cameras keyed by their id
+    pub fn get_available_cameras() -> Result<HashMap<String, CameraDevice>> {
         let mut devices_list = HashMap::new();
 
         let available_devices = nokhwa::query(match nokhwa::native_api_backend() {
@@ -38,7 +59,7 @@ impl ThreadedCamera {
                     tracing::error!(
                         "No Camera devices found. Setting default (No Device Found, -1)",
                     );
-                    devices_list.insert("No Device Found".to_string(), -1);
+                    devices_list.insert("No Device Found".to_string(), CameraDevice::none());
                 } else {
                     for device_info in available_devices {
                         tracing::warn!(
@@ -46,17 +67,22 @@ impl ThreadedCamera {
                             device_info.human_name(),
                             device_info.index()
                         );
-                        devices_list.insert(
-                            format!("{:<4} {}", device_info.human_name(), device_info.index()),
-                            match device_info.index().as_index() {
-                                Ok(index) => index as i32,
-                                Err(error) => {
-                                    tracing::error!("Unable to get camera index : {:?}, adding (No Device Found, -1)", error);
-                                    devices_list.insert("No Device Found".to_string(), -1);
-                                    return Ok(devices_list);
-                                }
-                            },
-                        );
+                        let index = match device_info.index().as_index() {
+                            Ok(index) => index as i32,
+                            Err(error) => {
+                                tracing::error!("Unable to get camera index : {:?}, adding (No Device Found, -1)", error);
+                                devices_list
+                                    .insert("No Device Found".to_string(), CameraDevice::none());
+                                return Ok(devices_list);
+                            }
+                        };
+
+                        // * The misc string holds the device path (Windows, macOS) or bus info (Linux), which don't depend on the plug order
+                        let id = match device_info.misc().trim() {
+                            "" => device_info.human_name(),
+                            misc => misc.to_owned(),
+                        };
+                        insert_camera(&mut devices_list, id, device_info.human_name(), index);
                     }
                 }
             }
@@ -73,67 +99,152 @@ impl ThreadedCamera {
         Ok(devices_list)
     }
 
-    pub fn start_camera_thread(
-        tx: Sender<Mat>,
-        camera_index: i32,
-        camera_name: String,
-    ) -> Result<Self> {
+    // Current index of the camera, it changes when cameras are plugged in a different order
+    pub fn resolve_camera_index(camera_id: &str) -> Result<i32> {
+        match ThreadedCamera::get_available_cameras()?.get(camera_id) {
+            Some(device) if device.index >= 0 => Ok(device.index),
+            _ => Err(anyhow::anyhow!("Camera {camera_id} not found")),
+        }
+    }
+
+    // Modes the camera can actually be opened with, highest resolution and frame rate first
+    pub fn get_supported_formats(camera_index: i32) -> Result<Vec<CaptureFormat>> {
+        let mut camera = Camera::new(
+            CameraIndex::Index(camera_index as u32),
+            RequestedFormat::new::<RgbFormat>(RequestedFormatType::None),
+        )
+        .map_err(|error| {
+            anyhow::anyhow!(
+                "Unable to open camera with index {camera_index} to query its formats : {:?}",
+                error
+            )
+        })?;
+
+        let camera_formats = camera.compatible_camera_formats().map_err(|error| {
+            anyhow::anyhow!(
+                "Unable to query formats of camera with index {camera_index} : {:?}",
+                error
+            )
+        })?;
+
+        let mut formats = camera_formats
+            .iter()
+            .map(|camera_format| CaptureFormat {
+                width: camera_format.width(),
+                height: camera_format.height(),
+                fps: camera_format.frame_rate(),
+                fourcc: match camera_format.format() {
+                    FrameFormat::MJPEG => "MJPG".to_owned(),
+                    FrameFormat::YUYV => "YUYV".to_owned(),
+                    FrameFormat::NV12 => "NV12".to_owned(),
+                    FrameFormat::GRAY => "GREY".to_owned(),
+                    other => format!("{:?}", other),
+                },
+            })
+            .collect::<Vec<CaptureFormat>>();
+
+        formats.sort_by(|a, b| {
+            (b.width * b.height, b.fps, &a.fourcc).cmp(&(a.width * a.height, a.fps, &b.fourcc))
+        });
+        formats.dedup();
+
+        Ok(formats)
+    }
+
+    // Polls the connected cameras in the background, calling on_event whenever a camera is plugged in or out
+    // The watcher stops once on_event returns false
+    pub fn watch_cameras<F>(interval: Duration, mut on_event: F)
+    where
+        F: FnMut(CameraEvent) -> bool + Send + 'static,
+    {
+        thread::spawn(move || {
+            let mut known_cameras = HashMap::new();
+
+            loop {
+                let cameras = match ThreadedCamera::get_available_cameras() {
+                    Ok(cameras) => cameras,
+                    Err(error) => {
+                        tracing::error!("{}", error);
+                        thread::sleep(interval);
+                        continue;
+                    }
+                };
+
+                for event in diff_cameras(&known_cameras, &cameras) {
+                    if !on_event(event) {
+                        return;
+                    }
+                }
+                known_cameras = cameras;
+
+                thread::sleep(interval);
+            }
+        });
+    }
+
+    // Reads frames from the source until shutdown, if reconnect is set a failing source is reopened instead of stopping the thread
+    // * Controls put in pending_controls by the GUI are applied to the source between two frames
+    pub fn start_source_thread(
+        mailbox: FrameMailbox,
+        mut source: Box<dyn FrameSource>,
+        reconnect: bool,
+        pending_controls: Arc<Mutex<Option<CameraControls>>>,
+    ) -> Self {
         // Serving as a signal to stop the thread when needed
         let keep_running = sync::Arc::new(AtomicBool::new(false));
         keep_running.store(true, Ordering::SeqCst);
 
         let cloned_keep_running = keep_running.clone();
 
-        let mut cam = match videoio::VideoCapture::new(camera_index, videoio::CAP_ANY) {
-            Ok(cam) => cam,
-            Err(error) => {
-                return Err(anyhow::anyhow!(
-                    "Unable to open camera {camera_name} with index {camera_index} : {:?}",
-                    error
-                ));
-            }
-        };
-        let opened = match videoio::VideoCapture::is_opened(&cam) {
-            Ok(opened) => opened,
-            Err(error) => {
-                return Err(anyhow::anyhow!(
-                    "Unable to open camera {camera_name} with index {camera_index} : {:?}",
-                    error
-                ));
-            }
-        };
-
-        if !opened {
-            return Err(anyhow::anyhow!("Unable to open the camera!"));
-        }
+        let (status_tx, status) = unbounded::<CameraStatus>();
 
         let cam_thread = Some(thread::spawn(move || {
             // Running loop as long as keep_running is true
             while cloned_keep_running.load(Ordering::SeqCst) {
+                if let Some(controls) = pending_controls.lock().unwrap().take() {
+                    if let Err(error) = source.apply_controls(&controls) {
+                        tracing::error!("Unable to apply {:?} : {:?}", controls, error);
+                    }
+                }
+
                 // Reading frame
                 let mut frame = Mat::default();
-                match cam.read(&mut frame) {
-                    Ok(_) => (),
+                match source.read(&mut frame) {
+                    Ok(true) => (),
+                    Ok(false) => {
+                        tracing::info!("No more frames to read from {}", source.name());
+                        break;
+                    }
                     Err(error) => {
-                        panic!("Unable to read frame from camera : {:?}", error);
-                        // return Err(anyhow::anyhow!(
-                        //     "Unable to read frame from camera : {:?}",
-                        //     error
-                        // ));
+                        tracing::error!(
+                            "Unable to read frame from {} : {:?}",
+                            source.name(),
+                            error
+                        );
+                        let _ = status_tx.send(CameraStatus::Disconnected(error.to_string()));
+
+                        if reconnect
+                            && reopen_source(source.as_mut(), &cloned_keep_running, &status_tx)
+                        {
+                            let _ = status_tx.send(CameraStatus::Connected);
+                            continue;
+                        }
+                        break;
                     }
                 }
 
-                // Send the frame to the other thread for processing
-                if tx.send(frame).is_err() {
-                    break;
-                }
+                // Handing the frame over for processing, replacing the previous one if it wasn't picked up yet
+                mailbox.post(Frame::new(frame));
             }
+
+            let _ = status_tx.send(CameraStatus::Ended);
         }));
 
-        Ok(Self {
+        Self {
             cam_thread,
             keep_running,
-        })
+            status,
+        }
     }
 
     pub fn shutdown(&mut self) {
@@ -156,20 +267,608 @@ impl ThreadedCamera {
     }
 }
 
+impl CameraControl {
+    pub const ALL: [CameraControl; 6] = [
+        CameraControl::Exposure,
+        CameraControl::Gain,
+        CameraControl::Brightness,
+        CameraControl::Contrast,
+        CameraControl::Focus,
+        CameraControl::WhiteBalance,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CameraControl::Exposure => "Exposure",
+            CameraControl::Gain => "Gain",
+            CameraControl::Brightness => "Brightness",
+            CameraControl::Contrast => "Contrast",
+            CameraControl::Focus => "Focus",
+            CameraControl::WhiteBalance => "White balance",
+        }
+    }
+
+    // Range of the slider, drivers clamp the values they don't support
+    pub fn range(&self) -> (f64, f64) {
+        match self {
+            #[cfg(target_os = "linux")]
+            CameraControl::Exposure => (1., 2000.),
+            #[cfg(not(target_os = "linux"))]
+            CameraControl::Exposure => (-13., 0.),
+            CameraControl::WhiteBalance => (2800., 6500.),
+            _ => (0., 255.),
+        }
+    }
+
+    // Value picked when switching the control from automatic to manual
+    pub fn default_value(&self) -> f64 {
+        match self {
+            #[cfg(target_os = "linux")]
+            CameraControl::Exposure => 156., // ~1/64 s
+            #[cfg(not(target_os = "linux"))]
+            CameraControl::Exposure => -6.,
+            CameraControl::WhiteBalance => 4600.,
+            _ => 128.,
+        }
+    }
+}
+
+impl CameraControls {
+    pub fn get(&self, control: CameraControl) -> Option<f64> {
+        match control {
+            CameraControl::Exposure => self.exposure,
+            CameraControl::Gain => self.gain,
+            CameraControl::Brightness => self.brightness,
+            CameraControl::Contrast => self.contrast,
+            CameraControl::Focus => self.focus,
+            CameraControl::WhiteBalance => self.white_balance,
+        }
+    }
+
+    pub fn set(&mut self, control: CameraControl, value: Option<f64>) {
+        match control {
+            CameraControl::Exposure => {
+                self.exposure = value;
+                // * A manual exposure only sticks once auto exposure is off
+                if value.is_some() {
+                    self.auto_exposure = Some(false);
+                }
+            }
+            CameraControl::Gain => self.gain = value,
+            CameraControl::Brightness => self.brightness = value,
+            CameraControl::Contrast => self.contrast = value,
+            CameraControl::Focus => self.focus = value,
+            CameraControl::WhiteBalance => self.white_balance = value,
+        }
+    }
+}
+
+// Retries opening the source with an exponential backoff, returns false if the thread was stopped meanwhile
+fn reopen_source(
+    source: &mut dyn FrameSource,
+    keep_running: &AtomicBool,
+    status_tx: &Sender<CameraStatus>,
+) -> bool {
+    let mut backoff = RECONNECT_MIN_BACKOFF;
+    let mut attempt = 1;
+
+    while keep_running.load(Ordering::SeqCst) {
+        let _ = status_tx.send(CameraStatus::Reconnecting(attempt));
+
+        // Sleeping in small steps, so that shutting down isn't delayed by the backoff
+        let retry_at = Instant::now() + backoff;
+        while Instant::now() < retry_at {
+            if !keep_running.load(Ordering::SeqCst) {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        match source.reopen() {
+            Ok(_) => {
+                tracing::info!(
+                    "Reconnected to {} after {} attempts",
+                    source.name(),
+                    attempt
+                );
+                return true;
+            }
+            Err(error) => {
+                tracing::warn!("Unable to reopen {} : {:?}", source.name(), error);
+            }
+        }
+
+        backoff = (backoff * 2).min(RECONNECT_MAX_BACKOFF);
+        attempt += 1;
+    }
+
+    false
+}
+
+// Adds the camera to the list under its id
+// * Webcams on different ports have different bus info, nodes sharing it belong to the same device
+// * Only the lowest one is kept, the others are metadata nodes that can't capture frames
+fn insert_camera(
+    devices_list: &mut HashMap<String, CameraDevice>,
+    id: String,
+    name: String,
+    index: i32,
+) {
+    if let Some(device) = devices_list.get(&id) {
+        if device.index <= index {
+            return;
+        }
+    }
+    devices_list.insert(id.clone(), CameraDevice { id, name, index });
+}
+
+// Cameras that appeared or disappeared between two camera lists, a camera whose index changed is connected again
+fn diff_cameras(
+    known_cameras: &HashMap<String, CameraDevice>,
+    cameras: &HashMap<String, CameraDevice>,
+) -> Vec<CameraEvent> {
+    let mut events = vec![];
+
+    for (id, device) in cameras {
+        if known_cameras.get(id) != Some(device) {
+            events.push(CameraEvent::Connected(device.clone()));
+        }
+    }
+    for id in known_cameras.keys() {
+        if !cameras.contains_key(id) {
+            events.push(CameraEvent::Disconnected(id.clone()));
+        }
+    }
+
+    events
+}
+
+// Finds the id of the camera a config key refers to
+// * Older configs used "{name:<4} {index}" as the key, the camera with that name is picked, preferably at the same index
+pub fn migrate_camera_key(key: &str, cameras: &HashMap<String, CameraDevice>) -> Option<String> {
+    if cameras.contains_key(key) {
+        return Some(key.to_owned());
+    }
+
+    let (name, index) = key.rsplit_once(' ')?;
+    let (name, index) = (name.trim_end(), index.parse::<i32>().ok()?);
+
+    let mut matching = cameras
+        .values()
+        .filter(|device| device.name == name)
+        .collect::<Vec<&CameraDevice>>();
+    matching.sort_by_key(|device| (device.index != index, device.index));
+
+    matching.first().map(|device| device.id.clone())
+}
+
+impl CaptureBackend {
+    pub const ALL: [CaptureBackend; 4] = [
+        CaptureBackend::Auto,
+        CaptureBackend::V4L2,
+        CaptureBackend::GStreamer(String::new()),
+        CaptureBackend::FFmpeg(String::new()),
+    ];
+
+    pub fn api(&self) -> VideoCaptureAPIs {
+        match self {
+            CaptureBackend::Auto => CAMERA_API,
+            CaptureBackend::V4L2 => VideoCaptureAPIs::CAP_V4L2,
+            CaptureBackend::GStreamer(_) => VideoCaptureAPIs::CAP_GSTREAMER,
+            CaptureBackend::FFmpeg(_) => VideoCaptureAPIs::CAP_FFMPEG,
+        }
+    }
+
+    // Pipeline or device opened instead of the selected camera
+    pub fn device(&self) -> Option<&str> {
+        match self {
+            CaptureBackend::GStreamer(device) | CaptureBackend::FFmpeg(device)
+                if !device.trim().is_empty() =>
+            {
+                Some(device.trim())
+            }
+            _ => None,
+        }
+    }
+
+    // Same backend without its pipeline/device, used to compare backends in the GUI
+    pub fn kind(&self) -> CaptureBackend {
+        match self {
+            CaptureBackend::GStreamer(_) => CaptureBackend::GStreamer(String::new()),
+            CaptureBackend::FFmpeg(_) => CaptureBackend::FFmpeg(String::new()),
+            backend => backend.clone(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !videoio::has_backend(self.api())? {
+            return Err(anyhow::anyhow!(
+                "OpenCV was built without the {} backend",
+                self
+            ));
+        }
+        if let (CaptureBackend::FFmpeg(_), None) = (self, self.device()) {
+            return Err(anyhow::anyhow!("FFmpeg needs a device path or URL to open"));
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for CaptureBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureBackend::Auto => write!(f, "Auto"),
+            CaptureBackend::V4L2 => write!(f, "V4L2"),
+            CaptureBackend::GStreamer(_) => write!(f, "GStreamer pipeline"),
+            CaptureBackend::FFmpeg(_) => write!(f, "FFmpeg"),
+        }
+    }
+}
+
+impl CaptureRoi {
+    // Whole frame, used as the starting point when the user enables the ROI
+    pub fn full() -> Self {
+        Self {
+            x: 0.,
+            y: 0.,
+            width: 1.,
+            height: 1.,
+        }
+    }
+
+    // Keeping the region inside the frame, shrinking the position before the size
+    pub fn clamped(self) -> Self {
+        let width = self.width.clamp(MIN_CAPTURE_ROI_SIZE, 1.);
+        let height = self.height.clamp(MIN_CAPTURE_ROI_SIZE, 1.);
+        Self {
+            x: self.x.clamp(0., 1. - width),
+            y: self.y.clamp(0., 1. - height),
+            width,
+            height,
+        }
+    }
+
+    // Region in pixels of a frame of the given size, never empty
+    pub fn to_rect(&self, frame_size: Size) -> Rect {
+        let roi = self.clamped();
+        let x = (roi.x * frame_size.width as f32) as i32;
+        let y = (roi.y * frame_size.height as f32) as i32;
+        let width = ((roi.width * frame_size.width as f32) as i32).clamp(1, frame_size.width - x);
+        let height =
+            ((roi.height * frame_size.height as f32) as i32).clamp(1, frame_size.height - y);
+        Rect::new(x, y, width, height)
+    }
+}
+
+impl CameraDevice {
+    // Placeholder when no camera is connected
+    pub fn none() -> Self {
+        Self {
+            id: "No Device Found".to_string(),
+            name: "No Device Found".to_string(),
+            index: -1,
+        }
+    }
+}
+
+impl std::fmt::Display for CameraDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.index {
+            -1 => write!(f, "{}", self.name),
+            index => write!(f, "{:<4} {}", self.name, index),
+        }
+    }
+}
+
 #[test]
 #[ignore = "Can only test this offline since it requires webcam, run cargo test -- --ignored"]
 pub fn test_threaded_camera() -> Result<()> {
-    let (tx, rx) = crossbeam_channel::unbounded::<Mat>();
+    use crate::structs::source::CameraSource;
+
+    let mailbox = FrameMailbox::new();
+    let mut frames = mailbox.subscribe_tracker();
 
     println!("{:?}", ThreadedCamera::get_available_cameras());
 
-    let mut thr_cam = ThreadedCamera::start_camera_thread(tx, 0, "Default Camera".to_owned())?;
+    println!("{:?}", ThreadedCamera::get_supported_formats(0));
+
+    let device = ThreadedCamera::get_available_cameras()?
+        .into_values()
+        .find(|device| device.index >= 0)
+        .ok_or(anyhow::anyhow!("No camera found"))?;
+    let camera = CameraSource::open(
+        device.id,
+        device.name,
+        Default::default(),
+        None,
+        Default::default(),
+    )?;
+    let mut thr_cam = ThreadedCamera::start_source_thread(
+        mailbox.clone(),
+        Box::new(camera),
+        true,
+        Arc::default(),
+    );
 
     for _ in 0..100 {
-        let _frame = rx.recv()?;
+        let _frame = frames
+            .recv_timeout(Duration::from_secs(5))
+            .ok_or(anyhow::anyhow!("No frame received from the camera"))?;
+    }
+
+    thr_cam.shutdown();
+    println!("{}", mailbox.stats);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_threaded_synthetic_source() -> Result<()> {
+    use crate::{enums::synthetic_pattern::SyntheticPattern, structs::source::SyntheticSource};
+
+    let mailbox = FrameMailbox::new();
+    let mut frames = mailbox.subscribe_tracker();
+
+    let source = SyntheticSource::new(SyntheticPattern::MovingRectangle, 640, 480, Some(50));
+    let mut thr_cam = ThreadedCamera::start_source_thread(
+        mailbox.clone(),
+        Box::new(source),
+        true,
+        Arc::default(),
+    );
+
+    // The thread stops once the source runs out of frames
+    assert_eq!(thr_cam.status.iter().last(), Some(CameraStatus::Ended));
+    assert_eq!(mailbox.stats.captured.load(Ordering::SeqCst), 50);
+
+    // Only the latest frame is kept, the ones that weren't picked up in time are dropped
+    assert!(frames.try_latest().is_some());
+    assert!(frames.try_latest().is_none());
+    assert_eq!(mailbox.stats.processed.load(Ordering::SeqCst), 1);
+    assert_eq!(mailbox.stats.dropped.load(Ordering::SeqCst), 49);
+
+    thr_cam.shutdown();
+
+    Ok(())
+}
+
+#[cfg(test)]
+fn test_cameras(devices: &[(&str, &str, i32)]) -> HashMap<String, CameraDevice> {
+    let mut cameras = HashMap::new();
+    for (id, name, index) in devices {
+        insert_camera(&mut cameras, id.to_string(), name.to_string(), *index);
+    }
+    cameras
+}
+
+#[test]
+pub fn test_diff_cameras() {
+    let known_cameras = test_cameras(&[
+        ("usb-0000:00:14.0-5", "Integrated Camera", 0),
+        ("usb-0000:00:14.0-1", "USB Camera", 2),
+    ]);
+
+    // Nothing changed
+    assert!(diff_cameras(&known_cameras, &known_cameras.clone()).is_empty());
+
+    // USB camera unplugged, IR camera plugged in
+    let cameras = test_cameras(&[
+        ("usb-0000:00:14.0-5", "Integrated Camera", 0),
+        ("usb-0000:00:14.0-2", "IR Camera", 4),
+    ]);
+    let events = diff_cameras(&known_cameras, &cameras);
+    assert_eq!(events.len(), 2);
+    assert!(events.contains(&CameraEvent::Connected(
+        cameras["usb-0000:00:14.0-2"].clone()
+    )));
+    assert!(events.contains(&CameraEvent::Disconnected("usb-0000:00:14.0-1".to_owned())));
+
+    // Every camera is new to an empty list
+    assert_eq!(diff_cameras(&HashMap::new(), &cameras).len(), 2);
+
+    // Same camera at another index after being replugged
+    let cameras = test_cameras(&[("usb-0000:00:14.0-5", "Integrated Camera", 1)]);
+    let events = diff_cameras(
+        &test_cameras(&[("usb-0000:00:14.0-5", "Integrated Camera", 0)]),
+        &cameras,
+    );
+    assert_eq!(
+        events,
+        vec![CameraEvent::Connected(
+            cameras["usb-0000:00:14.0-5"].clone()
+        )]
+    );
+
+    // Capture and metadata nodes of a webcam share their bus info, only the capture node is listed
+    let cameras = test_cameras(&[
+        ("usb-0000:00:14.0-3", "USB Camera", 3),
+        ("usb-0000:00:14.0-3", "USB Camera", 2),
+    ]);
+    assert_eq!(cameras.len(), 1);
+    assert_eq!(cameras["usb-0000:00:14.0-3"].index, 2);
+
+    // Identical webcams on different ports are both listed
+    let cameras = test_cameras(&[
+        ("usb-0000:00:14.0-3", "USB Camera", 2),
+        ("usb-0000:00:14.0-4", "USB Camera", 4),
+    ]);
+    assert_eq!(diff_cameras(&HashMap::new(), &cameras).len(), 2);
+}
+
+#[test]
+pub fn test_migrate_camera_key() {
+    let cameras = test_cameras(&[
+        ("usb-0000:00:14.0-5", "Integrated Camera", 0),
+        ("usb-0000:00:14.0-1", "USB Camera", 2),
+        ("usb-0000:00:14.0-2", "USB Camera", 4),
+    ]);
+
+    // Ids are kept as they are
+    assert_eq!(
+        migrate_camera_key("usb-0000:00:14.0-5", &cameras),
+        Some("usb-0000:00:14.0-5".to_owned())
+    );
+
+    // Legacy keys, the index only breaks ties between cameras with the same name
+    assert_eq!(
+        migrate_camera_key("Integrated Camera 3", &cameras),
+        Some("usb-0000:00:14.0-5".to_owned())
+    );
+    assert_eq!(
+        migrate_camera_key("USB Camera 4", &cameras),
+        Some("usb-0000:00:14.0-2".to_owned())
+    );
+    assert_eq!(
+        migrate_camera_key("USB Camera 0", &cameras),
+        Some("usb-0000:00:14.0-1".to_owned())
+    );
+    assert_eq!(migrate_camera_key("abc 0", &cameras), None);
+    assert_eq!(migrate_camera_key("Unknown Camera", &cameras), None);
+}
+
+#[test]
+pub fn test_threaded_camera_reconnect() -> Result<()> {
+    use crate::{enums::synthetic_pattern::SyntheticPattern, structs::source::SyntheticSource};
+
+    // A camera that gets unplugged after 5 frames and is only found again on the second attempt
+    struct FlakySource {
+        frames: SyntheticSource,
+        reads: u32,
+        reopen_attempts: u32,
+    }
+
+    impl FrameSource for FlakySource {
+        fn read(&mut self, frame: &mut Mat) -> Result<bool> {
+            self.reads += 1;
+            if self.reads == 6 {
+                return Err(anyhow::anyhow!("Camera unplugged"));
+            }
+            self.frames.read(frame)
+        }
+
+        fn reopen(&mut self) -> Result<()> {
+            self.reopen_attempts += 1;
+            match self.reopen_attempts {
+                1 => Err(anyhow::anyhow!("Camera not found")),
+                _ => Ok(()),
+            }
+        }
+
+        fn name(&self) -> String {
+            "Flaky Camera".to_owned()
+        }
     }
 
+    let mailbox = FrameMailbox::new();
+    let source = FlakySource {
+        frames: SyntheticSource::new(SyntheticPattern::MovingRectangle, 64, 48, Some(10)),
+        reads: 0,
+        reopen_attempts: 0,
+    };
+    let mut thr_cam = ThreadedCamera::start_source_thread(
+        mailbox.clone(),
+        Box::new(source),
+        true,
+        Arc::default(),
+    );
+
+    // Every frame is still captured, the disconnection only delays them
+    let statuses = thr_cam.status.iter().collect::<Vec<CameraStatus>>();
+    assert_eq!(mailbox.stats.captured.load(Ordering::SeqCst), 10);
+    assert_eq!(
+        statuses,
+        vec![
+            CameraStatus::Disconnected("Camera unplugged".to_owned()),
+            CameraStatus::Reconnecting(1),
+            CameraStatus::Reconnecting(2),
+            CameraStatus::Connected,
+            CameraStatus::Ended,
+        ]
+    );
+
+    thr_cam.shutdown();
+
+    // Without reconnecting, the thread stops at the first failure
+    let mailbox = FrameMailbox::new();
+    let source = FlakySource {
+        frames: SyntheticSource::new(SyntheticPattern::MovingRectangle, 64, 48, Some(10)),
+        reads: 0,
+        reopen_attempts: 0,
+    };
+    let mut thr_cam = ThreadedCamera::start_source_thread(
+        mailbox.clone(),
+        Box::new(source),
+        false,
+        Arc::default(),
+    );
+    assert_eq!(thr_cam.status.iter().last(), Some(CameraStatus::Ended));
+    assert_eq!(mailbox.stats.captured.load(Ordering::SeqCst), 5);
     thr_cam.shutdown();
 
     Ok(())
 }
+
+#[test]
+pub fn test_camera_controls() {
+    let mut controls = CameraControls::default();
+    assert_eq!(controls.get(CameraControl::Exposure), None);
+
+    // Setting the exposure manually turns auto exposure off
+    controls.set(CameraControl::Exposure, Some(100.));
+    assert_eq!(controls.get(CameraControl::Exposure), Some(100.));
+    assert_eq!(controls.auto_exposure, Some(false));
+
+    for control in CameraControl::ALL {
+        controls.set(control, Some(control.default_value()));
+        let (min, max) = control.range();
+        assert!((min..=max).contains(&controls.get(control).unwrap()));
+    }
+
+    // Older config files have no controls at all
+    let controls: CameraControls = serde_json::from_str("{}").unwrap();
+    assert_eq!(controls, CameraControls::default());
+}
+
+#[test]
+pub fn test_capture_backend() {
+    let pipeline = "v4l2src device=/dev/video2 ! videoconvert ! appsink";
+
+    // The pipeline replaces the selected camera, unless it is empty
+    let backend = CaptureBackend::GStreamer(format!(" {pipeline} "));
+    assert_eq!(backend.device(), Some(pipeline));
+    assert_eq!(backend.kind(), CaptureBackend::GStreamer(String::new()));
+    assert_eq!(CaptureBackend::GStreamer("  ".to_owned()).device(), None);
+    assert_eq!(CaptureBackend::Auto.device(), None);
+
+    // FFmpeg can't open cameras by index
+    assert!(CaptureBackend::FFmpeg(String::new()).validate().is_err());
+}
+
+#[test]
+pub fn test_capture_roi() {
+    let frame_size = Size::new(640, 480);
+    assert_eq!(
+        CaptureRoi::full().to_rect(frame_size),
+        Rect::new(0, 0, 640, 480)
+    );
+
+    // Centered half of the frame
+    let roi = CaptureRoi {
+        x: 0.25,
+        y: 0.25,
+        width: 0.5,
+        height: 0.5,
+    };
+    assert_eq!(roi.to_rect(frame_size), Rect::new(160, 120, 320, 240));
+
+    // Out of frame regions are moved back inside instead of being cropped
+    let roi = CaptureRoi {
+        x: 0.9,
+        y: -0.5,
+        width: 0.5,
+        height: 0.01,
+    }
+    .clamped();
+    assert_eq!((roi.x, roi.y), (0.5, 0.));
+    assert_eq!((roi.width, roi.height), (0.5, MIN_CAPTURE_ROI_SIZE));
+}
@@ -1,3 +1,6 @@
+use opencv::videoio::VideoCaptureAPIs;
+use std::time::Duration;
+
 pub const APP_NAME: &str = env!("CARGO_PKG_NAME");
 pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const APP_REPOSITORY: &str = env!("CARGO_PKG_REPOSITORY");
@@ -8,6 +11,76 @@ pub const APP_GITHUB_API: &str =
 pub const MODEL: &[u8] = include_bytes!("../assets/model/mb05_120x120.onnx");
 pub const DATA: &[u8] = include_bytes!("../assets/model/data.json");
 pub const BLAZE_FACE_MODEL: &[u8] = include_bytes!("../assets/model/blazeface-320.onnx");
+pub const FACE_DETECTOR_INPUT_SIZE: i32 = 320;
+
+pub const LANDMARK_COORDINATES: usize = 204; // x, y and z of the 68 landmarks
+
+pub const BUILTIN_MODEL_NAME: &str = "Built-in";
+pub const MODEL_MANIFEST_FILE: &str = "manifest.json";
+
+// How often the connected cameras are polled to detect cameras being plugged in or out
+pub const CAMERA_WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+// OpenCV backend opening cameras by the same indices nokhwa enumerates them with
+#[cfg(target_os = "linux")]
+pub const CAMERA_API: VideoCaptureAPIs = VideoCaptureAPIs::CAP_V4L2;
+#[cfg(target_os = "windows")]
+pub const CAMERA_API: VideoCaptureAPIs = VideoCaptureAPIs::CAP_MSMF;
+#[cfg(target_os = "macos")]
+pub const CAMERA_API: VideoCaptureAPIs = VideoCaptureAPIs::CAP_AVFOUNDATION;
+
+// Delay between attempts to reopen a disconnected camera, doubled after every failed attempt
+pub const RECONNECT_MIN_BACKOFF: Duration = Duration::from_millis(250);
+pub const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+// Network streams are reconnected to when they stall for longer than the read timeout
+pub const NETWORK_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+pub const NETWORK_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Smallest region of interest, as a fraction of the frame size
+pub const MIN_CAPTURE_ROI_SIZE: f32 = 0.1;
+
+// Values of CAP_PROP_AUTO_EXPOSURE, V4L2 takes its own menu values while other backends expect 0.75/0.25
+#[cfg(target_os = "linux")]
+pub const AUTO_EXPOSURE_ON: f64 = 3.;
+#[cfg(target_os = "linux")]
+pub const AUTO_EXPOSURE_OFF: f64 = 1.;
+#[cfg(not(target_os = "linux"))]
+pub const AUTO_EXPOSURE_ON: f64 = 0.75;
+#[cfg(not(target_os = "linux"))]
+pub const AUTO_EXPOSURE_OFF: f64 = 0.25;
+
+// Image sequences have no native frame rate, they are played at this rate (times the playback speed)
+pub const IMAGE_SEQUENCE_FPS: f64 = 30.;
+
+// Time taken to ease back to the center once the face has been lost for longer than the timeout
+pub const LOSS_EASE_DURATION: Duration = Duration::from_secs(1);
+
+// Root mean square of the standardized shape and expression parameters, at which a fit is no longer plausible
+pub const QUALITY_MAX_PARAM_Z: f32 = 3.;
+// Overlap of the landmarks with the tracked face box under which the fit is distrusted
+pub const QUALITY_MIN_IOU: f32 = 0.3;
+// Movement of the roi box in a frame, relative to its width, at which the fit is distrusted
+pub const QUALITY_MAX_JUMP: f32 = 0.5;
+// Frames dropped in a row after which the face counts as lost, the loss policy then takes over the pose
+pub const QUALITY_MAX_REJECTED_FRAMES: u32 = 10;
+
+// Similarities (0-1) a face needs to be taken for the person that was tracked before they were lost
+pub const REID_MIN_APPEARANCE: f32 = 0.5;
+pub const REID_MIN_SHAPE: f32 = 0.6;
+// How fast the signature of the tracked person follows changes of lighting, per frame
+pub const REID_UPDATE_RATE: f32 = 0.05;
+// Without the tracked person for this long, any face is taken again, the lighting may have changed meanwhile
+pub const REID_TIMEOUT: Duration = Duration::from_secs(10);
+
+// JPEG quality (0-100) of the frames streamed by the preview server
+pub const PREVIEW_JPEG_QUALITY: i32 = 80;
+
+// Preview clients that don't send their request or stop reading the stream for this long are dropped
+pub const PREVIEW_CLIENT_TIMEOUT: Duration = Duration::from_secs(3);
+
+// JPEG quality (0-100) of the frames in session recordings
+pub const RECORDING_QUALITY: f64 = 100.;
 
 pub const ICON: &[u8] = include_bytes!("../assets/brand/Product.ico");
 pub const INTER_FONT: &[u8] = include_bytes!("../assets/fonts/Inter-Regular.ttf");
@@ -0,0 +1,11 @@
+// Image controls of the camera that can be set manually, see CameraControls
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraControl {
+    Exposure,
+    Gain,
+    Brightness,
+    Contrast,
+    Focus,
+    WhiteBalance,
+}
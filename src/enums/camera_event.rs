@@ -0,0 +1,9 @@
+// Cameras being plugged in or out while the application is running
+
+use crate::structs::camera::CameraDevice;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CameraEvent {
+    Connected(CameraDevice),
+    Disconnected(String), // Id of the camera
+}
@@ -0,0 +1,9 @@
+// State of the capture thread, reported to the tracking loop
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CameraStatus {
+    Connected,
+    Disconnected(String), // Reason of the failure
+    Reconnecting(u32),    // Attempt number
+    Ended,                // The source has no more frames, or gave up reconnecting
+}
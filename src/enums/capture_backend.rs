@@ -0,0 +1,12 @@
+// OpenCV backend used to open the camera, the pipeline/device strings are used instead of the selected camera when not empty
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum CaptureBackend {
+    #[default]
+    Auto, // Native backend of the platform, matching the cameras listed
+    V4L2,
+    GStreamer(String), // Pipeline, e.g. "v4l2src device=/dev/video2 ! videoconvert ! appsink"
+    FFmpeg(String),    // Device path or URL
+}
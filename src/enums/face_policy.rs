@@ -0,0 +1,12 @@
+// Which face is tracked when there are several in the frame
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum FacePolicy {
+    #[default]
+    Sticky, // Closest to the face tracked in the previous frame, the largest one to start with
+    Largest,
+    ClosestToCenter,
+    Manual, // Picked by the user in the preview, then followed like Sticky
+}
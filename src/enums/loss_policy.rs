@@ -0,0 +1,11 @@
+// What is sent to opentrack while the face is lost
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum LossPolicy {
+    HoldLast,
+    #[default]
+    HoldThenCenter, // Holds the last pose, then eases back to the center once the timeout is over
+    SendNothing,
+}
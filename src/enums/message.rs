@@ -1,6 +1,15 @@
 // Events than can be triggered by the user in the GUI
 
 use iced::event::{Event};
+use iced::{Point, Size};
+
+use crate::{
+    enums::{
+        camera_control::CameraControl, camera_event::CameraEvent, capture_backend::CaptureBackend,
+        face_policy::FacePolicy, loss_policy::LossPolicy, rotation::Rotation,
+    },
+    structs::camera::{CaptureFormat, CaptureRoi},
+};
 
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -14,6 +23,39 @@ pub enum Message {
     InputPort(String),
     Camera(String),
     HideCamera(bool),
+    ToggleAdvanced,
+    InputPath(String),
+    PlaybackSpeedChanged(f32),
+    CaptureFormat(CaptureFormat),
+    DefaultCaptureFormat,
+    CaptureBackend(CaptureBackend),
+    CaptureBackendDevice(String),
+    Rotation(Rotation),
+    Mirror(bool),
+    Flip(bool),
+    CaptureRoi(Option<CaptureRoi>),
+    FacePolicy(FacePolicy),
+    Reidentify(bool),
+    PreviewClicked(Point, Size), // Position of the click in the preview, and the size of the preview
+    DetectionThreshold(f32),
+    NmsThreshold(f32),
+    DetectionIntervalChanged(u32),
+    MinQuality(f32),
+    LossPolicy(LossPolicy),
+    LossTimeout(f32), // In seconds
+    RecordSession(bool),
+    PreviewServer(bool),
+    PreviewPort(String),
+    PreviewLan(bool),
+    OpenRecordings,
+    Model(String),
+    ModelChecked(String, Result<(), String>), // Error of the model, shown in the GUI
+    OpenModels,
+    AutoExposure(bool),
+    CameraControl(CameraControl, Option<f64>),
+    DefaultCameraControls,
+    CameraChanged(CameraEvent),
+    AutoResume(bool),
     OpenURL(String),
     OpenLogs,
     EventOccurred(Event),
@@ -1,3 +1,12 @@
+pub mod camera_control;
+pub mod camera_event;
+pub mod camera_status;
+pub mod capture_backend;
 pub mod crop_policy;
 pub mod extreme;
+pub mod face_policy;
+pub mod loss_policy;
 pub mod message;
+pub mod rotation;
+pub mod synthetic_pattern;
+pub mod tracking_state;
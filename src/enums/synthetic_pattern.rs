@@ -0,0 +1,8 @@
+// Frames drawn by the synthetic frame source, none of them contain a face
+
+#[cfg(test)]
+#[derive(Debug, Clone, Copy)]
+pub enum SyntheticPattern {
+    SolidColor(f64, f64, f64), // BGR
+    MovingRectangle,
+}
@@ -0,0 +1,9 @@
+// Whether the tracker currently follows a face, shown in the GUI
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrackingState {
+    #[default]
+    Searching, // No face was found since tracking started
+    Tracking,
+    Lost, // The face was tracked, then disappeared
+}
@@ -6,9 +6,9 @@ use onnxruntime::environment::Environment;
 use onnxruntime::ndarray::{Array4, ArrayBase, Axis, Dim, OwnedRepr};
 use onnxruntime::tensor::OrtOwnedTensor;
 use onnxruntime::GraphOptimizationLevel;
-use opencv::prelude::MatTraitConstManual;
+use opencv::prelude::{MatTraitConst, MatTraitConstManual};
 use opencv::{
-    core::{Mat, Size, Vec3b},
+    core::{self, Mat, Scalar, Size, Vec3b},
     imgproc,
 };
 
@@ -17,8 +17,10 @@ use once_cell::sync::Lazy;
 
 use anyhow::Result;
 
-use crate::consts::BLAZE_FACE_MODEL;
-use crate::structs::face::FaceDetect;
+use crate::consts::FACE_DETECTOR_INPUT_SIZE;
+use crate::enums::face_policy::FacePolicy;
+use crate::structs::face::{FaceDetect, Letterbox};
+use crate::utils::image::to_bgr;
 
 use itertools::iproduct;
 
@@ -35,6 +37,20 @@ pub struct Face {
     pub landmarks: Option<Vec<(f32, f32)>>,
 }
 
+impl Face {
+    /// Moves the face, with its landmarks, by the given offset.
+    pub fn translate(&self, dx: f32, dy: f32) -> Face {
+        Face {
+            rect: self.rect.translate(dx, dy),
+            confidence: self.confidence,
+            landmarks: self
+                .landmarks
+                .as_ref()
+                .map(|landmarks| landmarks.iter().map(|(x, y)| (x + dx, y + dy)).collect()),
+        }
+    }
+}
+
 /// Non-maximum suppression.
 #[derive(Copy, Clone, Debug)]
 pub struct Nms {
@@ -147,6 +163,20 @@ impl PriorBoxes {
         let y_start = cy - height / 2.0;
         Rect::at(x_start, y_start).ending_at(width + x_start, height + y_start)
     }
+
+    // Keypoints are predicted as (x, y) offsets from the anchor center, like the box center
+    pub fn decode_landmarks(&self, prior: &(f32, f32, f32, f32), pred: &[f32]) -> Vec<(f32, f32)> {
+        let (anchor_cx, anchor_cy, s_kx, s_ky) = prior;
+
+        pred.chunks_exact(2)
+            .map(|point| {
+                (
+                    anchor_cx + point[0] * self.variances.0 * s_kx,
+                    anchor_cy + point[1] * self.variances.0 * s_ky,
+                )
+            })
+            .collect()
+    }
 }
 
 use std::fmt::Display;
@@ -211,6 +241,31 @@ impl Rect {
         intersection / (area_self + area_other - intersection)
     }
 
+    /// Center of the rectangle.
+    pub fn center(&self) -> (f32, f32) {
+        (self.x + self.width / 2., self.y + self.height / 2.)
+    }
+
+    /// Smallest rectangle containing the points.
+    pub fn around(xs: &[f32], ys: &[f32]) -> Rect {
+        let (left, right) = xs.iter().fold((f32::MAX, f32::MIN), |(min, max), &x| {
+            (min.min(x), max.max(x))
+        });
+        let (top, bottom) = ys.iter().fold((f32::MAX, f32::MIN), |(min, max), &y| {
+            (min.min(y), max.max(y))
+        });
+        Rect::at(left, top).ending_at(right, bottom)
+    }
+
+    /// Moves the rectangle by the given offset.
+    pub fn translate(&self, dx: f32, dy: f32) -> Rect {
+        Rect {
+            x: self.x + dx,
+            y: self.y + dy,
+            ..*self
+        }
+    }
+
     /// Scales the rectangle.
     pub fn scale(&self, x_scale: f32, y_scale: f32) -> Rect {
         Rect {
@@ -232,8 +287,114 @@ impl Display for Rect {
     }
 }
 
+// Squared distance between two points, only used to compare distances
+fn distance2(a: (f32, f32), b: (f32, f32)) -> f32 {
+    (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)
+}
+
+impl FacePolicy {
+    pub const ALL: [FacePolicy; 4] = [
+        FacePolicy::Sticky,
+        FacePolicy::Largest,
+        FacePolicy::ClosestToCenter,
+        FacePolicy::Manual,
+    ];
+
+    /// Picks the face to track among the detected ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `faces` - Faces detected in the frame.
+    /// * `previous` - Face tracked in the previous frame, if any.
+    /// * `target` - Point picked by the user in the preview, only used by `Manual`.
+    /// * `frame_size` - Size of the frame the faces were detected in.
+    pub fn select<'a>(
+        &self,
+        faces: &'a [Face],
+        previous: Option<Rect>,
+        target: Option<(f32, f32)>,
+        frame_size: Size,
+    ) -> Option<&'a Face> {
+        let closest_to = |point: (f32, f32)| {
+            faces.iter().min_by(|a, b| {
+                distance2(a.rect.center(), point).total_cmp(&distance2(b.rect.center(), point))
+            })
+        };
+        let largest = || {
+            faces.iter().max_by(|a, b| {
+                (a.rect.width * a.rect.height).total_cmp(&(b.rect.width * b.rect.height))
+            })
+        };
+
+        match (self, previous, target) {
+            (FacePolicy::Largest, _, _) => largest(),
+            (FacePolicy::ClosestToCenter, _, _) => {
+                closest_to((frame_size.width as f32 / 2., frame_size.height as f32 / 2.))
+            }
+            (FacePolicy::Manual, _, Some(target)) => closest_to(target),
+            (FacePolicy::Sticky | FacePolicy::Manual, Some(previous), _) => {
+                // * Faces overlapping the previous one are preferred, then the nearest one if the head moved a lot
+                let overlapping = faces
+                    .iter()
+                    .filter(|face| face.rect.iou(&previous) > 0.)
+                    .max_by(|a, b| a.rect.iou(&previous).total_cmp(&b.rect.iou(&previous)));
+                overlapping.or_else(|| closest_to(previous.center()))
+            }
+            (FacePolicy::Sticky | FacePolicy::Manual, None, _) => largest(),
+        }
+    }
+}
+
+impl Display for FacePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FacePolicy::Sticky => write!(f, "Keep the same face"),
+            FacePolicy::Largest => write!(f, "Largest face"),
+            FacePolicy::ClosestToCenter => write!(f, "Face closest to the center"),
+            FacePolicy::Manual => write!(f, "Face picked in the preview"),
+        }
+    }
+}
+
+impl Letterbox {
+    // Fitting the frame in the square input, centered
+    pub fn new(frame_size: Size, input_size: i32) -> Self {
+        let scale = (input_size as f32 / frame_size.width as f32)
+            .min(input_size as f32 / frame_size.height as f32);
+        let resized = Size::new(
+            ((frame_size.width as f32 * scale).round() as i32).clamp(1, input_size),
+            ((frame_size.height as f32 * scale).round() as i32).clamp(1, input_size),
+        );
+
+        Self {
+            frame_size,
+            resized,
+            left: (input_size - resized.width) / 2,
+            top: (input_size - resized.height) / 2,
+            input_size,
+        }
+    }
+
+    // From a point relative to the detector input (0 to 1) to pixels in the frame
+    pub fn point_to_frame(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let input_size = self.input_size as f32;
+        (
+            (x * input_size - self.left as f32) * self.frame_size.width as f32
+                / self.resized.width as f32,
+            (y * input_size - self.top as f32) * self.frame_size.height as f32
+                / self.resized.height as f32,
+        )
+    }
+
+    pub fn rect_to_frame(&self, rect: &Rect) -> Rect {
+        let (left, top) = self.point_to_frame((rect.x, rect.y));
+        let (right, bottom) = self.point_to_frame((rect.right(), rect.bottom()));
+        Rect::at(left, top).ending_at(right, bottom)
+    }
+}
+
 impl FaceDetect {
-    pub fn new() -> Result<Self> {
+    pub fn new(model: &[u8]) -> Result<Self> {
         static ENVIRONMENT: Lazy<Environment> = Lazy::new(|| {
             match Environment::builder()
                 .with_name("Face Detector")
@@ -252,15 +413,29 @@ impl FaceDetect {
             .new_session_builder()?
             .with_optimization_level(GraphOptimizationLevel::All)?
             .with_number_threads(1)?
-            .with_model_from_memory(BLAZE_FACE_MODEL)?;
+            .with_model_from_memory(model)?;
+
+        let priors = PriorBoxes::new(
+            &PriorBoxesParams::default(),
+            (
+                FACE_DETECTOR_INPUT_SIZE as usize,
+                FACE_DETECTOR_INPUT_SIZE as usize,
+            ),
+        );
 
-        Ok(Self { face_detector })
+        Ok(Self {
+            face_detector,
+            priors,
+            score_threshold: 0.5,
+            nms: Nms::default(),
+        })
     }
 
+    // * The frame is letterboxed rather than stretched, so faces keep their proportions whatever the camera resolution
     pub fn preprocess_frame(
         &self,
         frame: Mat,
-    ) -> Result<ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>> {
+    ) -> Result<(ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>, Letterbox)> {
         let bgr_frame = frame;
 
         // bgr to rgb on new frame
@@ -269,37 +444,54 @@ impl FaceDetect {
 
         // let cropped_image = crop_img(&bgr_frame, &[150., 150., 400., 400.])?;
 
+        let letterbox = Letterbox::new(bgr_frame.size()?, FACE_DETECTOR_INPUT_SIZE);
+
         // Resizing the frame
         let mut resized_frame = Mat::default();
         imgproc::resize(
             &bgr_frame,
             &mut resized_frame,
-            Size {
-                width: 320,
-                height: 320,
-            },
+            letterbox.resized,
             0.0,
             0.0,
             imgproc::INTER_LINEAR, //*INTER_AREA, // https://stackoverflow.com/a/51042104 | Speed -> https://stackoverflow.com/a/44278268
         )?; // ! Error handling here
 
-        let vec = Mat::data_typed::<Vec3b>(&resized_frame)?;
+        // Padding the rest of the input with black
+        let mut padded_frame = Mat::default();
+        core::copy_make_border(
+            &resized_frame,
+            &mut padded_frame,
+            letterbox.top,
+            letterbox.input_size - letterbox.resized.height - letterbox.top,
+            letterbox.left,
+            letterbox.input_size - letterbox.resized.width - letterbox.left,
+            core::BORDER_CONSTANT,
+            Scalar::all(0.),
+        )?;
+
+        // Mono (IR) and BGRA frames
+        let padded_frame = to_bgr(&padded_frame)?;
+
+        let vec = Mat::data_typed::<Vec3b>(&padded_frame)?;
+        let input_size = letterbox.input_size as usize;
 
         // use the shape [height, width, channels] instead of [channels, height, width].
         // Ok(Array3::from_shape_fn((120, 120, 3), |(y, x, c)| {
         //     Vec3b::deref(&vec[x + y * 120])[c]
         // }))
 
-        Ok(Array4::from_shape_fn((1, 3, 320, 320), |(_, c, y, x)| {
-            f32::from(Vec3b::deref(&vec[x + y * 320])[c])
-        }))
+        let array = Array4::from_shape_fn((1, 3, input_size, input_size), |(_, c, y, x)| {
+            f32::from(Vec3b::deref(&vec[x + y * input_size])[c])
+        });
+
+        Ok((array, letterbox))
     }
 
-    pub fn detect(&mut self, frame: Mat) -> Result<[f32; 4]> {
-        // -> Result<bool> {
-        // -> Result<Vec<Face>> {
-        let array = match self.preprocess_frame(frame) {
-            Ok(array) => vec![array],
+    // Every face in the frame, most confident first, the one to track is picked with a `FacePolicy`
+    pub fn detect(&mut self, frame: Mat) -> Result<Vec<Face>> {
+        let (array, letterbox) = match self.preprocess_frame(frame) {
+            Ok((array, letterbox)) => (vec![array], letterbox),
             Err(e) => {
                 tracing::error!("Error preprocessing frame: {:?}", e);
                 // return Ok(vec![]);
@@ -307,44 +499,54 @@ impl FaceDetect {
             }
         };
         let output_tensors: Vec<OrtOwnedTensor<f32, _>> = self.face_detector.run(array)?;
-        let boxes = &output_tensors[0];
-        let scores = &output_tensors[1];
-        let num_boxes = boxes.view().shape()[1];
-        let input_width = 320;
-        let input_height = 320;
-        let priors = PriorBoxes::new(
-            &PriorBoxesParams::default(),
-            (input_width as usize, input_height as usize),
-        );
-
-        let ratio = 320. / 640.0;
-        let scale_ratios = (input_width as f32 / ratio, input_height as f32 / ratio);
+        let num_boxes = output_tensors[0].view().shape()[1];
+        let boxes = output_tensors[0]
+            .view()
+            .to_shape((num_boxes, 4))?
+            .to_owned();
+        let scores = output_tensors[1]
+            .view()
+            .to_shape((num_boxes, 2))?
+            .to_owned();
+        // * Keypoints are only decoded when the model has a landmark output, the bundled one gives
+        // * 5 points (eyes, nose and mouth corners), BlazeFace front models give 6 (with the ears)
+        let keypoints = match output_tensors.get(2) {
+            Some(landmarks) => {
+                let values_per_box = landmarks.view().len() / num_boxes;
+                Some(
+                    landmarks
+                        .view()
+                        .to_shape((num_boxes, values_per_box))?
+                        .to_owned(),
+                )
+            }
+            None => None,
+        };
+        let priors = &self.priors;
 
-        // let faces: Vec<[f32; 4]> =
         let faces: Vec<Face> = boxes
-            .view()
-            .to_shape((num_boxes, 4))
-            .unwrap()
             .axis_iter(Axis(0))
             .zip(priors.anchors.iter())
-            .zip(
-                scores
-                    .view()
-                    .to_shape((num_boxes, 2))
-                    .unwrap()
-                    .axis_iter(Axis(0)),
-            )
-            .filter_map(|((rect, prior), score)| {
+            .zip(scores.axis_iter(Axis(0)))
+            .enumerate()
+            .filter_map(|(i, ((rect, prior), score))| {
                 let score = score[1];
 
-                if score > 0.5 {
+                if score > self.score_threshold {
+                    // Boxes are decoded relative to the detector input, then mapped back to the frame
                     let rect = priors.decode_box(prior, &(rect[0], rect[1], rect[2], rect[3]));
-                    let rect = rect.scale(scale_ratios.0, scale_ratios.1);
+                    let rect = letterbox.rect_to_frame(&rect);
+                    let landmarks = keypoints.as_ref().map(|keypoints| {
+                        priors
+                            .decode_landmarks(prior, &keypoints.row(i).to_vec())
+                            .into_iter()
+                            .map(|point| letterbox.point_to_frame(point))
+                            .collect()
+                    });
 
-                    // Some([rect.x, rect.y, rect.width, rect.height])
                     Some(Face {
                         rect,
-                        landmarks: None,
+                        landmarks,
                         confidence: score,
                     })
                 } else {
@@ -353,18 +555,136 @@ impl FaceDetect {
             })
             .collect_vec();
 
-        let nms = Nms::default();
-        let nms_faces = nms.suppress_non_maxima(faces);
-
-        if !nms_faces.is_empty() {
-            return Ok([
-                (nms_faces[0].rect.x),      // * 640.) / 120.,
-                (nms_faces[0].rect.y),      // * 480.) / 120.,
-                (nms_faces[0].rect.width),  // * 640.) / 120.,
-                (nms_faces[0].rect.height), // * 480.) / 120.,
-            ]);
-        } else {
-            return Ok([0.0; 4]);
-        }
+        Ok(self.nms.suppress_non_maxima(faces))
+    }
+}
+
+#[test]
+pub fn test_preprocess_frame_formats() -> Result<()> {
+    use opencv::core::{CV_16UC1, CV_8UC1, CV_8UC3, CV_8UC4};
+
+    let face_detector = FaceDetect::new(crate::consts::BLAZE_FACE_MODEL)?;
+
+    for typ in [CV_8UC3, CV_8UC1, CV_8UC4, CV_16UC1] {
+        let frame = Mat::new_rows_cols_with_default(480, 640, typ, Scalar::all(100.))?;
+        let (array, _) = face_detector.preprocess_frame(frame)?;
+        assert_eq!(array.shape(), &[1, 3, 320, 320]);
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn test_face_policy() {
+    let face = |x: f32, y: f32, size: f32| Face {
+        rect: Rect::at(x, y).ending_at(x + size, y + size),
+        confidence: 0.9,
+        landmarks: None,
+    };
+    // Player in the middle, a large poster on the left and someone far behind on the right
+    let faces = vec![
+        face(280., 200., 80.),
+        face(0., 0., 200.),
+        face(560., 100., 40.),
+    ];
+    let frame_size = Size::new(640, 480);
+    let picked = |policy: FacePolicy, previous: Option<Rect>, target: Option<(f32, f32)>| {
+        let face = policy.select(&faces, previous, target, frame_size).unwrap();
+        faces.iter().position(|other| other.rect.x == face.rect.x)
+    };
+
+    assert_eq!(picked(FacePolicy::Largest, None, None), Some(1));
+    assert_eq!(picked(FacePolicy::ClosestToCenter, None, None), Some(0));
+
+    // Sticky keeps the face overlapping the previous one, even if it isn't the largest
+    let previous = Rect::at(550., 110.).ending_at(590., 150.);
+    assert_eq!(picked(FacePolicy::Sticky, Some(previous), None), Some(2));
+    assert_eq!(picked(FacePolicy::Sticky, None, None), Some(1));
+
+    // Manual picks the face under the point clicked, then follows it like Sticky
+    assert_eq!(
+        picked(FacePolicy::Manual, None, Some((300., 240.))),
+        Some(0)
+    );
+    assert_eq!(picked(FacePolicy::Manual, Some(previous), None), Some(2));
+
+    assert!(FacePolicy::Largest
+        .select(&[], None, None, frame_size)
+        .is_none());
+}
+
+#[test]
+pub fn test_rect_around() {
+    let rect = Rect::around(&[30., 10., 20.], &[5., 40., 25.]);
+    assert_eq!(
+        (rect.x, rect.y, rect.right(), rect.bottom()),
+        (10., 5., 30., 40.)
+    );
+
+    // A single point makes an empty rectangle
+    let point = Rect::around(&[7.], &[9.]);
+    assert_eq!(
+        (point.x, point.y, point.width, point.height),
+        (7., 9., 0., 0.)
+    );
+}
+
+#[test]
+pub fn test_decode_landmarks() {
+    let priors = PriorBoxes::new(&PriorBoxesParams::default(), (320, 320));
+    let prior = (0.5, 0.5, 0.1, 0.2);
+
+    // No offset puts every keypoint on the anchor center
+    let points = priors.decode_landmarks(&prior, &[0.; 10]);
+    assert_eq!(points.len(), 5);
+    assert!(points.iter().all(|&point| point == (0.5, 0.5)));
+
+    let points = priors.decode_landmarks(&prior, &[1., -1., 2., 0.5]);
+    assert!((points[0].0 - 0.51).abs() < 1e-6 && (points[0].1 - 0.48).abs() < 1e-6);
+    assert!((points[1].0 - 0.52).abs() < 1e-6 && (points[1].1 - 0.51).abs() < 1e-6);
+
+    // Landmarks follow the face when it is moved back to the full frame
+    let face = Face {
+        rect: Rect::at(10., 10.).ending_at(20., 20.),
+        confidence: 0.9,
+        landmarks: Some(points),
     }
+    .translate(100., 50.);
+    assert_eq!(face.rect.x, 110.);
+    assert!((face.landmarks.unwrap()[0].0 - 100.51).abs() < 1e-4);
+}
+
+#[test]
+pub fn test_letterbox() -> Result<()> {
+    use opencv::core::CV_8UC3;
+
+    let face_detector = FaceDetect::new(crate::consts::BLAZE_FACE_MODEL)?;
+
+    // 4:3, 16:9 and portrait frames
+    for (width, height, left, top) in [(640, 480, 0, 40), (1280, 720, 0, 70), (480, 640, 40, 0)] {
+        let letterbox = Letterbox::new(Size::new(width, height), 320);
+        assert_eq!((letterbox.left, letterbox.top), (left, top));
+
+        // The part of the input holding the frame maps back to the whole frame
+        let content = Rect::at(left as f32 / 320., top as f32 / 320.)
+            .ending_at(1. - left as f32 / 320., 1. - top as f32 / 320.);
+        let rect = letterbox.rect_to_frame(&content);
+        assert!(rect.x.abs() < 1e-3 && rect.y.abs() < 1e-3);
+        assert!((rect.width - width as f32).abs() < 1e-3);
+        assert!((rect.height - height as f32).abs() < 1e-3);
+
+        // A square face stays square in the frame
+        let face = letterbox.rect_to_frame(&Rect::at(0.4, 0.4).ending_at(0.6, 0.6));
+        assert!((face.width - face.height).abs() < 1e-3);
+        let (x, y) = face.center();
+        assert!((x - width as f32 / 2.).abs() < 1e-3 && (y - height as f32 / 2.).abs() < 1e-3);
+
+        // The padding is black and the frame itself is kept
+        let frame = Mat::new_rows_cols_with_default(height, width, CV_8UC3, Scalar::all(100.))?;
+        let (array, _) = face_detector.preprocess_frame(frame)?;
+        assert_eq!(array[[0, 0, 0, 0]], if left + top > 0 { 0. } else { 100. });
+        assert_eq!(array[[0, 0, 160, 160]], 100.);
+    }
+
+    Ok(())
 }
@@ -3,6 +3,8 @@
 /// The pseudocode is originajlly from https://github.com/jaantollander/OneEuroFilter, which is further modified for our use case  
 use std::f32;
 
+use crate::structs::frame::PoseSample;
+
 // ! Need Default values
 struct OneEuroFilter {
     // Parameters
@@ -85,12 +87,14 @@ impl EuroDataFilter {
         }
     }
 
+    // Filters the pose, keeping the capture time and sequence number of the sample
     pub fn filter_data(
         &mut self,
-        data: [f32; 6],
+        sample: PoseSample,
         min_cutoff: Option<f32>,
         beta: Option<f32>,
-    ) -> [f32; 6] {
+    ) -> PoseSample {
+        let data = sample.data;
         let mut filtered_data = [0.; 6];
 
         filtered_data[0] = self.x.run(data[0], min_cutoff, beta);
@@ -100,7 +104,10 @@ impl EuroDataFilter {
         filtered_data[4] = self.pitch.run(data[4], min_cutoff, beta);
         filtered_data[5] = self.roll.run(data[5], min_cutoff, beta);
 
-        filtered_data
+        PoseSample {
+            data: filtered_data,
+            ..sample
+        }
     }
 }
 
@@ -0,0 +1,31 @@
+/// Timestamped frames and poses, used to measure the end-to-end latency of the pipeline
+use crate::structs::frame::{Frame, PoseSample};
+
+use opencv::prelude::Mat;
+use std::time::{Duration, Instant};
+
+impl Frame {
+    // Stamps the frame with the current time, the sequence number is given by the mailbox
+    pub fn new(mat: Mat) -> Self {
+        Self {
+            mat,
+            seq: 0,
+            captured_at: Instant::now(),
+        }
+    }
+}
+
+impl PoseSample {
+    pub fn new(data: [f32; 6], frame: &Frame) -> Self {
+        Self {
+            data,
+            seq: frame.seq,
+            captured_at: frame.captured_at,
+        }
+    }
+
+    // Time since the frame this pose was estimated from was captured
+    pub fn latency(&self) -> Duration {
+        self.captured_at.elapsed()
+    }
+}
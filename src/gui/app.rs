@@ -1,25 +1,47 @@
 // Handing the events and updating the state of the application
 
-use crate::consts::APP_NAME;
-use crate::gui::view::run_page;
+use crate::consts::{APP_NAME, CAMERA_WATCH_INTERVAL};
+use crate::gui::preview_area::click_to_frame;
+use crate::gui::view::{advanced_page, run_page};
 use crate::{
-    enums::message::Message,
+    enums::{
+        camera_event::CameraEvent, camera_status::CameraStatus, capture_backend::CaptureBackend,
+        message::Message, tracking_state::TrackingState,
+    },
     filter::EuroDataFilter,
-    structs::{app::HeadTracker, state::AppConfig},
+    source::open_frame_source,
+    structs::{
+        app::HeadTracker,
+        camera::{CameraControls, CameraDevice, CaptureRoi},
+        model::ModelSet,
+        preview_server::PreviewServer,
+        recorder::SessionRecorder,
+        state::AppConfig,
+        tracking::PoseHold,
+    },
     structs::{camera::ThreadedCamera, network::SocketNetwork, pose::ProcessHeadPose},
+    utils::{
+        image::to_bgr,
+        visualize::{draw_keypoints, draw_landmark},
+    },
 };
 use iced::{
     executor,  widget::Container, Application,  Command, Element, Length,
     Theme,
 };
 use iced::{mouse, window};
+use opencv::prelude::MatTraitConst;
 use std::{
-    sync::atomic::Ordering,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
     thread,
     time::{Duration, Instant},
 };
-use iced::Subscription;
+use iced::{subscription, Subscription};
 use iced::event::{self, Event};
+use iced::futures::{channel::oneshot, executor::block_on, SinkExt};
 
 // Log the error and break the block expression
 macro_rules! trace_error {
@@ -35,7 +57,8 @@ impl Application for HeadTracker {
     type Message = Message;
     type Theme = Theme;
 
-    fn new(flags: HeadTracker) -> (HeadTracker, Command<Message>) {
+    fn new(mut flags: HeadTracker) -> (HeadTracker, Command<Message>) {
+        flags.refresh_camera_formats();
         (flags, Command::none())
     }
 
@@ -45,7 +68,7 @@ impl Application for HeadTracker {
 
     fn subscription(&self) -> Subscription<Message> {
         // If camera is hidden, only listen for events, otherwise listen for events and ticks to update camera frame in GUI
-        match self.config.hide_camera {
+        let events = match self.config.hide_camera {
             true => event::listen().map(Message::EventOccurred),
             false => {
                 if self.headtracker_running.load(Ordering::SeqCst) {
@@ -57,7 +80,9 @@ impl Application for HeadTracker {
                     event::listen().map(Message::EventOccurred)
                 }
             }
-        }
+        };
+
+        Subscription::batch(vec![events, camera_watcher()])
     }
 
     fn theme(&self) -> Theme {
@@ -84,22 +109,36 @@ impl Application for HeadTracker {
                     // Setting headtracker to running
                     self.headtracker_running.store(true, Ordering::SeqCst);
 
-                    // Getting the index of the selected camera
-                    let camera_index = match self.camera_list.get(&self.config.selected_camera) {
-                        Some(index) => *index,
-                        // ! Should this be 0 or something else ?
+                    // Getting the selected camera, its index is looked up again when it opens
+                    let camera = match self.camera_list.get(&self.config.selected_camera) {
+                        Some(camera) => camera.clone(),
+                        // * Opening fails with a "not found" error if the camera is still missing by then
                         None => {
-                            tracing::error!("Unable to find camera index, setting default to 0");
-                            0
+                            tracing::error!(
+                                "Camera {} is not connected",
+                                self.config.selected_camera
+                            );
+                            CameraDevice {
+                                id: self.config.selected_camera.clone(),
+                                name: self.config.selected_camera.clone(),
+                                index: -1,
+                            }
                         }
                     };
 
                     // Copying the necessary data to the thread
-                    let camera_name = self.config.selected_camera.clone();
                     let config = self.config.clone();
                     let headtracker_running = self.headtracker_running.clone();
-                    let tx = self.sender.clone();
-                    let rx = self.receiver.clone();
+                    let mailbox = self.frames.clone();
+                    // * The camera opens with the controls from the config, older pending changes may be for another camera
+                    *self.pending_controls.lock().unwrap() = None;
+                    let pending_controls = self.pending_controls.clone();
+                    let recording = self.recording.clone();
+                    let detected_faces = self.detected_faces.clone();
+                    *self.picked_face.lock().unwrap() = None;
+                    let picked_face = self.picked_face.clone();
+                    *self.tracking_state.lock().unwrap() = TrackingState::default();
+                    let tracking_state = self.tracking_state.clone();
                     let error_tracker = self.error_tracker.clone();
 
                     // Spawning the thread
@@ -130,60 +169,209 @@ impl Application for HeadTracker {
                                     }
                                 };
 
-                            // Create a channel to communicate between threads
-                            let mut thr_cam = match ThreadedCamera::start_camera_thread(
-                                tx,
-                                camera_index,
-                                camera_name,
-                            ) {
-                                Ok(camera) => camera,
+                            // Loading the models first, so a broken one is reported before the camera opens
+                            let models = match ModelSet::load(&config.model) {
+                                Ok(models)
+                                    if models.name != config.model && !config.model.is_empty() =>
+                                {
+                                    // * Shown without stopping the tracking, the bundled model is used instead
+                                    *error_tracker.lock().unwrap() = format!(
+                                        "The model {} wasn't found, using the built-in one",
+                                        config.model
+                                    );
+                                    models
+                                }
+                                Ok(models) => models,
                                 Err(error) => {
-                                    // If an error occurs, set the error message and break the block expression
+                                    error_message = format!("{error:#}");
                                     trace_error!(error);
                                     break 'inner;
                                 }
                             };
 
-                            let mut head_pose = match ProcessHeadPose::new(120) {
-                                Ok(pose) => pose,
+                            // Opening the video file if one is set, otherwise the camera
+                            let capture_format = config.capture_formats.get(&camera.id).cloned();
+                            let controls = config
+                                .camera_controls
+                                .get(&camera.id)
+                                .cloned()
+                                .unwrap_or_default();
+                            // Rotation/mirroring in the frames, a replayed recording already has its own
+                            let frame_transform = SessionRecorder::read_header(
+                                std::path::Path::new(&config.input_path),
+                            )
+                            .map_or(config.frame_transform, |header| header.frame_transform);
+                            let source = match open_frame_source(
+                                &config.input_path,
+                                config.playback_speed,
+                                camera,
+                                config.capture_backend.clone(),
+                                capture_format,
+                                controls,
+                                config.frame_transform,
+                            ) {
+                                Ok(source) => source,
                                 Err(error) => {
                                     // If an error occurs, set the error message and break the block expression
+                                    // * Shown in the GUI, e.g. when OpenCV lacks the selected backend
+                                    error_message = error.to_string();
                                     trace_error!(error);
                                     break 'inner;
                                 }
                             };
 
-                            // Getting the first frame from the camera, if an error occurs, set the error message and use an empty frame
-                            let mut frame = match rx.recv() {
-                                Ok(result) => result,
+                            // Serving the annotated preview to browsers, if enabled
+                            let mut preview_server = None;
+                            if config.preview_server {
+                                let server = config
+                                    .preview_port
+                                    .trim()
+                                    .parse::<u16>()
+                                    .map_err(|_| {
+                                        anyhow::anyhow!(
+                                            "Invalid preview port {}",
+                                            config.preview_port
+                                        )
+                                    })
+                                    .and_then(|port| {
+                                        PreviewServer::start(port, config.preview_lan)
+                                    });
+                                match server {
+                                    Ok(server) => preview_server = Some(server),
+                                    Err(error) => {
+                                        error_message = error.to_string();
+                                        trace_error!(error);
+                                        break 'inner;
+                                    }
+                                }
+                            }
+
+                            // Create a channel to communicate between threads
+                            // * When auto resume is on, the capture thread keeps trying to reopen a camera that was unplugged
+                            let stats = mailbox.stats.clone();
+                            stats.reset();
+                            let mut frames = mailbox.subscribe_tracker();
+                            let mut thr_cam = ThreadedCamera::start_source_thread(
+                                mailbox,
+                                source,
+                                config.auto_resume,
+                                pending_controls,
+                            );
+
+                            let mut head_pose = match ProcessHeadPose::new(&models) {
+                                Ok(pose) => pose,
                                 Err(error) => {
-                                    error_message =
-                                        format!("Unable to receive image data: {}", error);
-                                    tracing::error!(error_message);
-                                    opencv::core::Mat::default()
+                                    // If an error occurs, set the error message and break the block expression
+                                    // * The model files passed the checks, but the runtime may still reject them
+                                    error_message = format!("{error:#}");
+                                    thr_cam.shutdown();
+                                    trace_error!(error);
+                                    break 'inner;
                                 }
                             };
 
-                            // Contains x, y, z, yaw, pitch, roll
-                            let mut data;
+                            head_pose.face_policy = config.face_policy;
+                            head_pose.reidentify = config.reidentify;
+
+                            // Contains x, y, z, yaw, pitch, roll, along with the capture time of the frame
+                            let mut sample;
+
+                            // Pose sent in place of the missing ones while the face is lost
+                            let mut pose_hold = PoseHold::new();
+
+                            // Frames and poses saved for bug reports while recording is on
+                            let mut recorder: Option<SessionRecorder> = None;
+
+                            // Whether the capture thread is currently delivering frames
+                            let mut camera_connected = true;
 
                             // Looping until headtracker_running is set to false ( ie. user clicks on the Stop button )
                             while headtracker_running.load(Ordering::SeqCst) {
                                 let start_time = Instant::now();
 
-                                // Getting the frame from the camera, if an error occurs, use the previous frame
-                                frame = match rx.try_recv() {
-                                    Ok(result) => result,
-                                    Err(_) => frame.clone(),
+                                // Showing the camera disconnections and reconnections to the user
+                                let mut source_ended = false;
+                                for status in thr_cam.status.try_iter() {
+                                    match status {
+                                        CameraStatus::Connected => {
+                                            camera_connected = true;
+                                            *error_tracker.lock().unwrap() = String::new();
+                                        }
+                                        CameraStatus::Disconnected(reason) => {
+                                            camera_connected = false;
+                                            error_message =
+                                                format!("Camera disconnected: {}", reason);
+                                            *error_tracker.lock().unwrap() = error_message.clone();
+                                        }
+                                        CameraStatus::Reconnecting(attempt) => {
+                                            *error_tracker.lock().unwrap() = format!(
+                                                "Camera disconnected, retrying... (attempt {})",
+                                                attempt
+                                            );
+                                        }
+                                        CameraStatus::Ended => source_ended = true,
+                                    }
+                                }
+                                if camera_connected {
+                                    error_message.clear();
+                                }
+
+                                // The capture thread stopped, either the video ended or the camera couldn't be reopened
+                                if source_ended {
+                                    break;
+                                }
+
+                                // Not processing the last frame over and over while the camera is away
+                                if !camera_connected {
+                                    thread::sleep(Duration::from_millis(50));
+                                    continue;
+                                }
+
+                                // Waiting for a new frame from the camera, the statuses are checked again if none arrives
+                                let frame = match frames.recv_timeout(Duration::from_millis(100)) {
+                                    Some(frame) => frame,
+                                    None => continue,
                                 };
 
                                 // Getting the head pose from the frame
+                                head_pose.capture_roi = *config.capture_roi.lock().unwrap();
+                                head_pose.face_detector.score_threshold =
+                                    config.detection_threshold.load(Ordering::SeqCst);
+                                head_pose.face_detector.nms.iou_threshold =
+                                    config.nms_threshold.load(Ordering::SeqCst);
+                                head_pose.detection_interval =
+                                    config.detection_interval.load(Ordering::SeqCst);
+                                head_pose.min_quality = config.min_quality.load(Ordering::SeqCst);
+                                let frame_size = frame.mat.size().unwrap_or_default();
+                                let (width, height) =
+                                    (frame_size.width as f32, frame_size.height as f32);
+                                if let Some((x, y)) = picked_face.lock().unwrap().take() {
+                                    head_pose.manual_target = Some((x * width, y * height));
+                                    // * Someone else is tracked from now on
+                                    head_pose.signature = None;
+                                    head_pose.tracked_face = None;
+                                }
                                 let out = head_pose.single_iter(&frame);
 
+                                // Faces shown in the preview, to pick the one to track
+                                *detected_faces.lock().unwrap() = head_pose
+                                    .detected_faces
+                                    .iter()
+                                    .map(|face| {
+                                        [
+                                            face.rect.x / width,
+                                            face.rect.y / height,
+                                            face.rect.right() / width,
+                                            face.rect.bottom() / height,
+                                        ]
+                                    })
+                                    .collect();
+
                                 // If an error occurs, skip the loop
                                 match out {
                                     Ok(value) => {
-                                        data = value;
+                                        sample = value;
+                                        *tracking_state.lock().unwrap() = head_pose.tracking_state;
                                     }
                                     Err(_) => {
                                         // println!("An error: {}; skipped.", e);
@@ -196,25 +384,103 @@ impl Application for HeadTracker {
                                     }
                                 };
 
-                                // Smoothing and Filtering the data
-                                data = euro_filter.filter_data(
-                                    data,
-                                    Some(config.min_cutoff.load(Ordering::SeqCst)),
-                                    Some(config.beta.load(Ordering::SeqCst)),
+                                // Publishing the frame with the landmarks, detector keypoints and face box drawn on it
+                                // * Only annotated while someone is watching
+                                if let Some(server) =
+                                    preview_server.as_ref().filter(|server| server.is_watched())
+                                {
+                                    let published = to_bgr(&frame.mat)
+                                        .map_err(anyhow::Error::from)
+                                        .and_then(|mat| {
+                                            draw_landmark(
+                                                mat,
+                                                head_pose.pts_3d.clone(),
+                                                head_pose.face_box,
+                                                (0., 255., 0.),
+                                                1,
+                                            )
+                                        })
+                                        .and_then(|mat| {
+                                            let keypoints = head_pose
+                                                .detected_faces
+                                                .iter()
+                                                .filter_map(|face| face.landmarks.clone())
+                                                .flatten()
+                                                .collect::<Vec<_>>();
+                                            draw_keypoints(mat, &keypoints, (255., 0., 255.), 2)
+                                        })
+                                        .and_then(|annotated| server.publish(&annotated));
+                                    if let Err(error) = published {
+                                        tracing::error!("Unable to publish the preview : {error}");
+                                    }
+                                }
+
+                                // Holding the last pose while the face is lost, nothing is sent when the policy says so
+                                let raw_sample = sample;
+                                let held_sample = pose_hold.apply(
+                                    head_pose.tracking_state,
+                                    sample,
+                                    config.loss_policy,
+                                    Duration::from_secs_f32(
+                                        config.loss_timeout.load(Ordering::SeqCst),
+                                    ),
                                 );
 
-                                // Sending the data to OpenTrack, if an error occurs, set the error message and break the loop
-                                match socket_network.send(data) {
-                                    Ok(_) => {}
-                                    Err(_) => {
-                                        error_message = format!(
-                                            "Unable to send data to {}:{}",
-                                            &config.ip, &config.port
-                                        );
-                                        tracing::error!(error_message);
-                                        break;
+                                // Smoothing and Filtering the data
+                                if let Some(held_sample) = held_sample {
+                                    sample = euro_filter.filter_data(
+                                        held_sample,
+                                        Some(config.min_cutoff.load(Ordering::SeqCst)),
+                                        Some(config.beta.load(Ordering::SeqCst)),
+                                    );
+                                }
+
+                                // Recording the session, started and stopped from the GUI
+                                if recording.load(Ordering::SeqCst) {
+                                    if recorder.is_none() {
+                                        recorder = SessionRecorder::recordings_dir()
+                                            .and_then(|directory| {
+                                                SessionRecorder::start(
+                                                    &directory,
+                                                    config.fps.load(Ordering::SeqCst) as f64,
+                                                    frame_transform,
+                                                )
+                                            })
+                                            .map_err(|error| {
+                                                stop_recording(&recording, &error_tracker, error)
+                                            })
+                                            .ok();
                                     }
-                                };
+                                    if let Some(session) = recorder.as_mut() {
+                                        if let Err(error) = session.record(
+                                            &frame,
+                                            head_pose.face_box,
+                                            head_pose.roi_box,
+                                            &raw_sample,
+                                            held_sample.map(|_| &sample),
+                                        ) {
+                                            stop_recording(&recording, &error_tracker, error);
+                                        }
+                                    }
+                                }
+                                if !recording.load(Ordering::SeqCst) {
+                                    finish_recording(recorder.take());
+                                }
+
+                                // Sending the data to OpenTrack, if an error occurs, set the error message and break the loop
+                                if held_sample.is_some() {
+                                    match socket_network.send(&sample) {
+                                        Ok(latency) => stats.record_latency(latency),
+                                        Err(_) => {
+                                            error_message = format!(
+                                                "Unable to send data to {}:{}",
+                                                &config.ip, &config.port
+                                            );
+                                            tracing::error!(error_message);
+                                            break;
+                                        }
+                                    };
+                                }
 
                                 // Calculating the delay time and sleeping for that amount of time, Used to set the fps
                                 let elapsed_time = start_time.elapsed();
@@ -226,6 +492,10 @@ impl Application for HeadTracker {
                             }
 
                             thr_cam.shutdown();
+                            finish_recording(recorder);
+                            if let Some(mut server) = preview_server {
+                                server.shutdown();
+                            }
                         }
 
                         // Setting the error message
@@ -250,10 +520,9 @@ impl Application for HeadTracker {
 
             // If camera is set visible, get the frame and show it in the GUI
             Message::Tick => {
-                self.frame = match self.receiver.try_recv() {
-                    Ok(result) => result,
-                    Err(_) => self.frame.clone(),
-                };
+                if let Some(frame) = self.preview.try_latest() {
+                    self.frame = frame.mat;
+                }
             }
 
             // Deals with the filter values
@@ -289,26 +558,159 @@ impl Application for HeadTracker {
                 self.config.port = port;
                 self.save_config()
             }
+            Message::PreviewServer(value) => {
+                self.config.preview_server = value;
+                self.restart_headtracker();
+                self.save_config()
+            }
+            Message::PreviewPort(port) => {
+                self.config.preview_port = port;
+                self.save_config()
+            }
+            Message::PreviewLan(value) => {
+                self.config.preview_lan = value;
+                self.restart_headtracker();
+                self.save_config()
+            }
 
             Message::Camera(camera_name) => {
                 self.config.selected_camera = camera_name;
 
-                // If camera changes while running
-                if self.headtracker_running.load(Ordering::SeqCst) {
-                    // Turn it back off and on again :)
+                // If camera changes while running, turn it back off and on again :)
+                // * The formats can only be queried while the camera is not in use
+                let running = self.headtracker_running.load(Ordering::SeqCst);
+                if running {
                     #[allow(unused_must_use)]
                     {
                         self.update(Message::Toggle);
+                    }
+                }
+                self.refresh_camera_formats();
+                if running {
+                    #[allow(unused_must_use)]
+                    {
                         self.update(Message::Toggle);
                     }
                 }
 
                 self.save_config()
             }
+            Message::CaptureFormat(capture_format) => {
+                self.config
+                    .capture_formats
+                    .insert(self.config.selected_camera.clone(), capture_format);
+                self.restart_headtracker();
+                self.save_config()
+            }
+            Message::CameraChanged(CameraEvent::Connected(camera)) => {
+                tracing::info!("Camera connected : {} ({})", camera, camera.id);
+                self.camera_list.insert(camera.id.clone(), camera.clone());
+
+                // Switching to the new camera if no camera was ever selected
+                // * A selected camera that is unplugged stays selected, so it's used again once it's back
+                if camera.index >= 0
+                    && (self.config.selected_camera.is_empty()
+                        || self.config.selected_camera == "No Device Found")
+                {
+                    self.config.selected_camera = camera.id.clone();
+                }
+                // * Tracking itself is resumed by the capture thread, which keeps reopening the camera
+                if camera.id == self.config.selected_camera
+                    && !self.headtracker_running.load(Ordering::SeqCst)
+                {
+                    self.refresh_camera_formats();
+                }
+            }
+            Message::CameraChanged(CameraEvent::Disconnected(camera_id)) => {
+                tracing::info!("Camera disconnected : {}", camera_id);
+                self.camera_list.remove(&camera_id);
+            }
+            Message::AutoResume(value) => {
+                self.config.auto_resume = value;
+                self.restart_headtracker();
+                self.save_config()
+            }
+            Message::DefaultCaptureFormat => {
+                self.config
+                    .capture_formats
+                    .remove(&self.config.selected_camera);
+                self.restart_headtracker();
+                self.save_config()
+            }
+            Message::CaptureBackend(backend) => {
+                // * Keeping the pipeline/device when the same backend is picked again
+                if backend.kind() != self.config.capture_backend.kind() {
+                    self.config.capture_backend = backend;
+                    self.restart_headtracker();
+                }
+                self.save_config()
+            }
+            Message::CaptureBackendDevice(device) => {
+                self.config.capture_backend = match self.config.capture_backend {
+                    CaptureBackend::GStreamer(_) => CaptureBackend::GStreamer(device),
+                    CaptureBackend::FFmpeg(_) => CaptureBackend::FFmpeg(device),
+                    ref backend => backend.clone(),
+                };
+                self.save_config()
+            }
+            Message::Rotation(rotation) => {
+                self.config.frame_transform.rotation = rotation;
+                self.restart_headtracker();
+                self.save_config()
+            }
+            Message::Mirror(value) => {
+                self.config.frame_transform.mirror = value;
+                self.restart_headtracker();
+                self.save_config()
+            }
+            Message::Flip(value) => {
+                self.config.frame_transform.flip = value;
+                self.restart_headtracker();
+                self.save_config()
+            }
+            Message::CaptureRoi(capture_roi) => {
+                *self.config.capture_roi.lock().unwrap() = capture_roi.map(CaptureRoi::clamped);
+                self.save_config()
+            }
+            Message::AutoExposure(value) => {
+                self.update_camera_controls(|controls| {
+                    controls.auto_exposure = Some(value);
+                    // * Letting the driver pick the exposure again
+                    if value {
+                        controls.exposure = None;
+                    }
+                });
+                self.save_config()
+            }
+            Message::CameraControl(control, value) => {
+                self.update_camera_controls(|controls| controls.set(control, value));
+                self.save_config()
+            }
+            Message::DefaultCameraControls => {
+                self.config
+                    .camera_controls
+                    .remove(&self.config.selected_camera);
+                // * Writing the automatic modes back, the camera would keep the manual values otherwise
+                *self.pending_controls.lock().unwrap() = Some(CameraControls::default());
+                self.save_config()
+            }
             Message::HideCamera(value) => {
                 self.config.hide_camera = value;
                 self.save_config()
             }
+            Message::ToggleAdvanced => {
+                self.show_advanced = !self.show_advanced;
+                // Models may have been added to the models directory meanwhile
+                self.model_list = ModelSet::available();
+            }
+            Message::InputPath(path) => {
+                self.config.input_path = path;
+                self.save_config()
+            }
+            Message::PlaybackSpeedChanged(speed) => {
+                self.config.playback_speed = speed;
+                self.save_config()
+            }
 
             Message::DefaultSettings => {
                 self.config
@@ -323,6 +725,36 @@ impl Application for HeadTracker {
                 self.config.ip = AppConfig::default().ip;
                 self.config.port = AppConfig::default().port;
                 self.config.hide_camera = AppConfig::default().hide_camera;
+                self.config.input_path = AppConfig::default().input_path;
+                self.config.playback_speed = AppConfig::default().playback_speed;
+                self.config.capture_formats = AppConfig::default().capture_formats;
+                self.config.camera_controls = AppConfig::default().camera_controls;
+                self.config.capture_backend = AppConfig::default().capture_backend;
+                self.config.frame_transform = AppConfig::default().frame_transform;
+                self.config.preview_server = AppConfig::default().preview_server;
+                self.config.preview_port = AppConfig::default().preview_port;
+                self.config.preview_lan = AppConfig::default().preview_lan;
+                self.config.face_policy = AppConfig::default().face_policy;
+                self.config.reidentify = AppConfig::default().reidentify;
+                self.config
+                    .detection_threshold
+                    .store(AppConfig::default().detection_threshold, Ordering::SeqCst);
+                self.config
+                    .nms_threshold
+                    .store(AppConfig::default().nms_threshold, Ordering::SeqCst);
+                self.config
+                    .detection_interval
+                    .store(AppConfig::default().detection_interval, Ordering::SeqCst);
+                self.config
+                    .min_quality
+                    .store(AppConfig::default().min_quality, Ordering::SeqCst);
+                self.config.loss_policy = AppConfig::default().loss_policy;
+                self.config.model = AppConfig::default().model;
+                self.config
+                    .loss_timeout
+                    .store(AppConfig::default().loss_timeout, Ordering::SeqCst);
+                *self.config.capture_roi.lock().unwrap() = AppConfig::default().capture_roi;
+                self.config.auto_resume = AppConfig::default().auto_resume;
 
                 self.save_config();
             }
@@ -372,6 +804,129 @@ impl Application for HeadTracker {
                     }
                 }
             }
+            Message::FacePolicy(policy) => {
+                self.config.face_policy = policy;
+                self.restart_headtracker();
+                self.save_config()
+            }
+            Message::Reidentify(value) => {
+                self.config.reidentify = value;
+                self.restart_headtracker();
+                self.save_config()
+            }
+            Message::DetectionThreshold(value) => {
+                self.config
+                    .detection_threshold
+                    .store(value, Ordering::SeqCst);
+                self.save_config()
+            }
+            Message::NmsThreshold(value) => {
+                self.config.nms_threshold.store(value, Ordering::SeqCst);
+                self.save_config()
+            }
+            Message::DetectionIntervalChanged(interval) => {
+                self.config
+                    .detection_interval
+                    .store(interval, Ordering::SeqCst);
+                self.save_config()
+            }
+            Message::MinQuality(value) => {
+                self.config.min_quality.store(value, Ordering::SeqCst);
+                self.save_config()
+            }
+            Message::LossPolicy(policy) => {
+                self.config.loss_policy = policy;
+                self.restart_headtracker();
+                self.save_config()
+            }
+            Message::LossTimeout(timeout) => {
+                self.config.loss_timeout.store(timeout, Ordering::SeqCst);
+                self.save_config()
+            }
+            Message::PreviewClicked(position, bounds) => {
+                // * The tracking thread picks the detected face closest to the click
+                let frame_size = self.frame.size().unwrap_or_default();
+                let frame_size = iced::Size::new(frame_size.width as f32, frame_size.height as f32);
+                if let Some(point) = click_to_frame(position, bounds, frame_size) {
+                    *self.picked_face.lock().unwrap() = Some(point);
+                }
+            }
+            Message::RecordSession(value) => {
+                self.recording.store(value, Ordering::SeqCst);
+            }
+            Message::Model(name) => {
+                // Checking the model before switching to it, off the GUI thread as its files are read and checksummed
+                let (sender, receiver) = oneshot::channel();
+                let checked_name = name.clone();
+                thread::spawn(move || {
+                    let checked = ModelSet::load(&checked_name)
+                        .map(|_| ())
+                        .map_err(|error| format!("{error:#}"));
+                    let _ = sender.send(checked);
+                });
+                return Command::perform(
+                    async move {
+                        receiver
+                            .await
+                            .unwrap_or_else(|_| Err("Unable to check the model".to_owned()))
+                    },
+                    move |checked| Message::ModelChecked(name, checked),
+                );
+            }
+            Message::ModelChecked(name, checked) => match checked {
+                Ok(()) => {
+                    self.config.model = name;
+                    self.restart_headtracker();
+                    self.save_config()
+                }
+                Err(error) => {
+                    tracing::error!("Unable to load the model {name} : {error}");
+
+                    let mut error_guard = self.error_tracker.lock().unwrap();
+                    *error_guard = error;
+                }
+            },
+            Message::OpenModels => {
+                #[cfg(target_os = "windows")]
+                let program = "explorer";
+                #[cfg(target_os = "macos")]
+                let program = "open";
+                #[cfg(target_os = "linux")]
+                let program = "xdg-open";
+
+                let opened = ModelSet::models_dir().and_then(|directory| {
+                    std::fs::create_dir_all(&directory)?;
+                    std::process::Command::new(program).arg(directory).spawn()?;
+                    Ok(())
+                });
+                if let Err(e) = opened {
+                    tracing::error!("Unable to open models directory : {:?}", e);
+
+                    let mut error_guard = self.error_tracker.lock().unwrap();
+                    *error_guard = String::from("Unable to open models directory");
+                }
+            }
+            Message::OpenRecordings => {
+                #[cfg(target_os = "windows")]
+                let program = "explorer";
+                #[cfg(target_os = "macos")]
+                let program = "open";
+                #[cfg(target_os = "linux")]
+                let program = "xdg-open";
+
+                // * The directory is only created by the first recording
+                let opened = SessionRecorder::recordings_dir().and_then(|directory| {
+                    std::fs::create_dir_all(&directory)?;
+                    std::process::Command::new(program).arg(directory).spawn()?;
+                    Ok(())
+                });
+                if let Err(e) = opened {
+                    tracing::error!("Unable to open recordings directory : {:?}", e);
+
+                    let mut error_guard = self.error_tracker.lock().unwrap();
+                    *error_guard = String::from("Unable to open recordings directory");
+                }
+            }
             Message::EventOccurred(event) => {
                 // If the user request to close the window, stop the thread ( if running ) and exit the program
                 if let Event::Window(_, window::Event::CloseRequested) = event {
@@ -392,19 +947,10 @@ impl Application for HeadTracker {
                     std::process::exit(0);
                 }
 
-                // TODO : Refresh the camera list when use clicks anywhere in the app, need better approach,
+                // Clearing the error message when the user clicks anywhere in the app
                 if let Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) = event {
                     let mut error_guard = self.error_tracker.lock().unwrap();
                     *error_guard = String::new();
-
-                    // Updating camera list
-                    match ThreadedCamera::get_available_cameras() {
-                        Ok(camera_list) => self.camera_list = camera_list,
-                        Err(e) => {
-                            tracing::error!("{}", e);
-                            *error_guard = e.to_string();
-                        }
-                    }
                 }
             }
         }
@@ -412,7 +958,10 @@ impl Application for HeadTracker {
     }
 
     fn view(&self) -> Element<Message> {
-        let body = run_page(self);
+        let body = match self.show_advanced {
+            true => advanced_page(self),
+            false => run_page(self),
+        };
 
         Container::new(body)
             .width(Length::Fill)
@@ -422,3 +971,63 @@ impl Application for HeadTracker {
             .into()
     }
 }
+
+// Background watcher updating the camera list when cameras are plugged in or out
+fn camera_watcher() -> Subscription<Message> {
+    struct CameraWatcher;
+
+    subscription::channel(
+        std::any::TypeId::of::<CameraWatcher>(),
+        10,
+        |mut output| async move {
+            ThreadedCamera::watch_cameras(CAMERA_WATCH_INTERVAL, move |event| {
+                // Waiting for room in the channel, a dropped event would never be reported again
+                // * Stopping the watcher once the GUI is no longer listening
+                block_on(output.send(Message::CameraChanged(event))).is_ok()
+            });
+
+            iced::futures::future::pending().await
+        },
+    )
+}
+
+// Turns recording off when the session can't be recorded, tracking keeps going
+fn stop_recording(recording: &AtomicBool, error_tracker: &Mutex<String>, error: anyhow::Error) {
+    tracing::error!("Unable to record the session: {error}");
+    *error_tracker.lock().unwrap() = format!("Recording stopped: {error}");
+    recording.store(false, Ordering::SeqCst);
+}
+
+fn finish_recording(recorder: Option<SessionRecorder>) {
+    if let Some(recorder) = recorder {
+        if let Err(error) = recorder.finish() {
+            tracing::error!("Unable to finish the recording: {error}");
+        }
+    }
+}
+
+impl HeadTracker {
+    // Applies settings that are only read when the camera opens
+    fn restart_headtracker(&mut self) {
+        // If running, turn it back off and on again :)
+        if self.headtracker_running.load(Ordering::SeqCst) {
+            #[allow(unused_must_use)]
+            {
+                self.update(Message::Toggle);
+                self.update(Message::Toggle);
+            }
+        }
+    }
+
+    // Changes the controls of the selected camera, and hands them to the camera thread if it is running
+    fn update_camera_controls(&mut self, change: impl FnOnce(&mut CameraControls)) {
+        let controls = self
+            .config
+            .camera_controls
+            .entry(self.config.selected_camera.clone())
+            .or_default();
+        change(controls);
+
+        *self.pending_controls.lock().unwrap() = Some(controls.clone());
+    }
+}
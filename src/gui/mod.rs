@@ -1,3 +1,4 @@
 pub mod app;
+pub mod preview_area;
 pub mod style;
 pub mod view;
@@ -0,0 +1,220 @@
+/// Preview that can be clicked, to pick the face to track
+/// * iced doesn't give the position of a click on an image, so the image is wrapped in this widget
+use iced::{
+    advanced::{
+        layout, renderer,
+        widget::{Operation, Tree},
+        Clipboard, Layout, Shell, Widget,
+    },
+    event, mouse, ContentFit, Element, Event, Length, Point, Rectangle, Size,
+};
+
+pub struct PreviewArea<'a, Message, Theme = iced::Theme, Renderer = iced::Renderer> {
+    content: Element<'a, Message, Theme, Renderer>,
+    on_click: Option<Box<dyn Fn(Point, Size) -> Message + 'a>>, // Position of the click in the bounds, and the bounds
+}
+
+impl<'a, Message, Theme, Renderer> PreviewArea<'a, Message, Theme, Renderer> {
+    pub fn new(content: impl Into<Element<'a, Message, Theme, Renderer>>) -> Self {
+        Self {
+            content: content.into(),
+            on_click: None,
+        }
+    }
+
+    pub fn on_click(mut self, on_click: impl Fn(Point, Size) -> Message + 'a) -> Self {
+        self.on_click = Some(Box::new(on_click));
+        self
+    }
+}
+
+impl<'a, Message, Theme, Renderer> Widget<Message, Theme, Renderer>
+    for PreviewArea<'a, Message, Theme, Renderer>
+where
+    Renderer: renderer::Renderer,
+{
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.content)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(std::slice::from_ref(&self.content));
+    }
+
+    fn size(&self) -> Size<Length> {
+        self.content.as_widget().size()
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        self.content
+            .as_widget()
+            .layout(&mut tree.children[0], renderer, limits)
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn Operation<Message>,
+    ) {
+        self.content
+            .as_widget()
+            .operate(&mut tree.children[0], layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        if let event::Status::Captured = self.content.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event.clone(),
+            layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        ) {
+            return event::Status::Captured;
+        }
+
+        if let (Some(on_click), Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))) =
+            (&self.on_click, event)
+        {
+            let bounds = layout.bounds();
+            if let Some(position) = cursor.position_in(bounds) {
+                shell.publish(on_click(position, bounds.size()));
+                return event::Status::Captured;
+            }
+        }
+
+        event::Status::Ignored
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        if self.on_click.is_some() && cursor.is_over(layout.bounds()) {
+            return mouse::Interaction::Pointer;
+        }
+        self.content.as_widget().mouse_interaction(
+            &tree.children[0],
+            layout,
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.content.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            layout,
+            cursor,
+            viewport,
+        );
+    }
+}
+
+impl<'a, Message, Theme, Renderer> From<PreviewArea<'a, Message, Theme, Renderer>>
+    for Element<'a, Message, Theme, Renderer>
+where
+    Message: 'a,
+    Theme: 'a,
+    Renderer: 'a + renderer::Renderer,
+{
+    fn from(area: PreviewArea<'a, Message, Theme, Renderer>) -> Self {
+        Element::new(area)
+    }
+}
+
+// Position of the click as fractions of the frame, None outside of it
+// * The image is scaled to fit the bounds and centered, as ContentFit::Contain does
+pub fn click_to_frame(position: Point, bounds: Size, frame_size: Size) -> Option<(f32, f32)> {
+    if frame_size.width <= 0. || frame_size.height <= 0. {
+        return None;
+    }
+
+    let shown = ContentFit::Contain.fit(frame_size, bounds);
+    let (left, top) = (
+        (bounds.width - shown.width) / 2.,
+        (bounds.height - shown.height) / 2.,
+    );
+    let (x, y) = (
+        (position.x - left) / shown.width,
+        (position.y - top) / shown.height,
+    );
+
+    match (0. ..=1.).contains(&x) && (0. ..=1.).contains(&y) {
+        true => Some((x, y)),
+        false => None,
+    }
+}
+
+#[test]
+pub fn test_click_to_frame() {
+    // 640x480 frame shown in a 400x200 preview, scaled to 266x200 and centered
+    let bounds = Size::new(400., 200.);
+    let frame_size = Size::new(640., 480.);
+    let shown_width = 200. * 640. / 480.;
+    let left = (400. - shown_width) / 2.;
+
+    assert_eq!(
+        click_to_frame(Point::new(200., 100.), bounds, frame_size),
+        Some((0.5, 0.5))
+    );
+    let (x, y) = click_to_frame(
+        Point::new(left + shown_width / 4., 150.),
+        bounds,
+        frame_size,
+    )
+    .unwrap();
+    assert!((x - 0.25).abs() < 1e-5 && (y - 0.75).abs() < 1e-5);
+
+    // Bars on the sides of the frame
+    assert_eq!(
+        click_to_frame(Point::new(10., 100.), bounds, frame_size),
+        None
+    );
+    assert_eq!(
+        click_to_frame(Point::new(390., 100.), bounds, frame_size),
+        None
+    );
+
+    // No frame yet
+    assert_eq!(
+        click_to_frame(Point::new(200., 100.), bounds, Size::ZERO),
+        None
+    );
+}
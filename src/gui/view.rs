@@ -5,13 +5,29 @@ use std::{borrow::Cow, sync::atomic::Ordering};
 use iced::{
     alignment::{self, Horizontal, Vertical},
     widget::{
-        button, pick_list, slider, text, text_input, toggler, Column, Container, Row, Space, Text,
+        button, pick_list, scrollable, slider, text, text_input, toggler, Column, Container, Row,
+        Space, Text,
     },
     Alignment, Length, Renderer,
 };
-use opencv::{core::VectorToVec, imgcodecs};
+use opencv::{
+    core::{MatTraitConst, Rect, Scalar, VectorToVec},
+    imgcodecs, imgproc,
+    prelude::Mat,
+};
 
-use crate::{consts::NO_VIDEO_IMG, enums::message::Message, structs::app::HeadTracker};
+use crate::{
+    consts::NO_VIDEO_IMG,
+    enums::{
+        camera_control::CameraControl, capture_backend::CaptureBackend, face_policy::FacePolicy,
+        loss_policy::LossPolicy, message::Message, rotation::Rotation,
+    },
+    gui::preview_area::PreviewArea,
+    structs::{
+        app::HeadTracker,
+        camera::{CameraDevice, CaptureRoi},
+    },
+};
 
 use super::style::{HEIGHT_BODY, HEIGHT_FOOTER};
 use crate::consts::{APP_AUTHORS, APP_NAME, APP_REPOSITORY, APP_VERSION};
@@ -100,7 +116,11 @@ pub fn run_page(headtracker: &HeadTracker) -> Column<Message> {
         true => NO_VIDEO_IMG.to_vec(),
         false => {
             if headtracker.headtracker_running.load(Ordering::SeqCst) {
-                let frame = headtracker.frame.clone();
+                let mut frame = headtracker.frame.clone();
+                if let Err(e) = annotate_preview(&mut frame, headtracker) {
+                    tracing::error!("Error annotating the preview: {}", e);
+                }
+
                 let mut encoded_image = opencv::core::Vector::<u8>::new();
                 let params = opencv::core::Vector::<i32>::new();
                 match imgcodecs::imencode(".PNG", &frame, &mut encoded_image, &params) {
@@ -116,28 +136,54 @@ pub fn run_page(headtracker: &HeadTracker) -> Column<Message> {
         }
     };
 
+    // Cameras sorted by name, so that the list doesn't reorder itself as cameras come and go
+    let mut cameras = headtracker
+        .camera_list
+        .values()
+        .cloned()
+        .collect::<Vec<CameraDevice>>();
+    cameras.sort_by(|a, b| (&a.name, a.index).cmp(&(&b.name, b.index)));
+
+    // Whether a face is tracked, so the user knows why the in-game camera stopped moving
+    let tracking_status = match headtracker.headtracker_running.load(Ordering::SeqCst) {
+        true => text(format!("{}", *headtracker.tracking_state.lock().unwrap())).size(14),
+        false => text(""),
+    };
+
+    // The face to track is picked by clicking it in the preview
+    let pick_face = headtracker.config.face_policy == FacePolicy::Manual
+        && headtracker.headtracker_running.load(Ordering::SeqCst)
+        && !hide_camera;
+    let face_picker = match pick_face {
+        true => text("Click a face in the preview to track it").size(14),
+        false => text(""),
+    };
+    let mut preview = PreviewArea::new(
+        iced::widget::image(iced::widget::image::Handle::from_memory(image))
+            .width(Length::Fill)
+            .height(Length::Fixed(200.)),
+    );
+    if pick_face {
+        preview = preview.on_click(Message::PreviewClicked);
+    }
+
     // Contains camera placeholder, available cameras list and the toggle button to hide the camera
     let camera_row = Container::new(
         Column::new()
-            .push({
-                iced::widget::image::viewer(iced::widget::image::Handle::from_memory(image))
-                    .width(Length::Fill)
-                    .height(Length::Fixed(200.))
-            })
+            .push(preview)
+            .push(tracking_status)
+            .push(face_picker)
             .push(Space::with_height(Length::Fixed(32.)))
             .push(Container::new(
                 Row::new()
                     .push(
                         pick_list(
-                            Cow::from(
-                                headtracker
-                                    .camera_list
-                                    .keys()
-                                    .cloned()
-                                    .collect::<Vec<String>>(),
-                            ),
-                            Some(headtracker.config.selected_camera.clone()),
-                            Message::Camera,
+                            Cow::from(cameras),
+                            headtracker
+                                .camera_list
+                                .get(&headtracker.config.selected_camera)
+                                .cloned(),
+                            |camera: CameraDevice| Message::Camera(camera.id),
                         )
                         .width(Length::FillPortion(50)),
                     )
@@ -184,6 +230,8 @@ pub fn run_page(headtracker: &HeadTracker) -> Column<Message> {
                         None => Container::new(Space::with_height(Length::Fixed(40.))),
                     })
                     .push(Space::with_width(Length::Fixed(34.)))
+                    .push(button(text("  Advanced  ").size(15)).on_press(Message::ToggleAdvanced))
+                    .push(Space::with_width(Length::Fixed(10.)))
                     .push(
                         button(text("  Reset to Default  ").size(15))
                             .on_press(Message::DefaultSettings),
@@ -216,6 +264,522 @@ pub fn run_page(headtracker: &HeadTracker) -> Column<Message> {
     Column::new().spacing(10).push(body).push(footer)
 }
 
+// Settings that are rarely changed, kept away from the main page
+pub fn advanced_page(headtracker: &HeadTracker) -> Column<Message> {
+    let input_path = headtracker.config.input_path.as_str();
+    let playback_speed = headtracker.config.playback_speed;
+
+    let input_section = Column::new()
+        .push(text("Input").size(15))
+        .push(Space::with_height(Length::Fixed(20.)))
+        .push(
+            text("Video file, image directory or stream URL (http:// MJPEG, rtsp://), leave empty to use the camera")
+                .size(14),
+        )
+        .push(text_input("recording.mp4", input_path).on_input(Message::InputPath))
+        .push(Space::with_height(Length::Fixed(10.)))
+        .push(
+            text(format!(
+                "Playback speed : {playback_speed}x (0 plays as fast as possible)"
+            ))
+            .size(14),
+        )
+        .push(slider(0.0..=8.0, playback_speed, Message::PlaybackSpeedChanged).step(0.25));
+
+    // Modes supported by the selected camera, the driver picks its default one when none is selected
+    let capture_format = headtracker
+        .config
+        .capture_formats
+        .get(&headtracker.config.selected_camera)
+        .cloned();
+
+    let capture_section = Column::new()
+        .push(text("Camera Format").size(15))
+        .push(Space::with_height(Length::Fixed(20.)))
+        .push(text("Resolution, frame rate and pixel format of the selected camera").size(14))
+        .push(
+            Row::new()
+                .spacing(5)
+                .push(
+                    pick_list(
+                        Cow::from(headtracker.camera_formats.clone()),
+                        capture_format,
+                        Message::CaptureFormat,
+                    )
+                    .placeholder("Driver default")
+                    .width(Length::FillPortion(70)),
+                )
+                .push(
+                    button(text("Driver default").size(14)).on_press(Message::DefaultCaptureFormat),
+                ),
+        )
+        .push(Space::with_height(Length::Fixed(10.)))
+        .push(text("Capture backend").size(14))
+        .push(
+            pick_list(
+                Cow::from(CaptureBackend::ALL.to_vec()),
+                Some(headtracker.config.capture_backend.kind()),
+                Message::CaptureBackend,
+            )
+            .width(Length::Fill),
+        );
+
+    // Pipeline or device opened instead of the selected camera
+    let capture_section = match &headtracker.config.capture_backend {
+        CaptureBackend::GStreamer(device) => capture_section.push(
+            text_input(
+                "v4l2src device=/dev/video0 ! videoconvert ! appsink",
+                device,
+            )
+            .on_input(Message::CaptureBackendDevice),
+        ),
+        CaptureBackend::FFmpeg(device) => capture_section
+            .push(text_input("/dev/video0", device).on_input(Message::CaptureBackendDevice)),
+        _ => capture_section,
+    };
+
+    let capture_section = capture_section
+        .push(Space::with_height(Length::Fixed(10.)))
+        .push(
+            toggler(
+                "Keep tracking when the camera is unplugged and plugged back in".to_string(),
+                headtracker.config.auto_resume,
+                Message::AutoResume,
+            )
+            .size(20)
+            .text_size(14),
+        );
+
+    // Image controls of the selected camera, each one is left to the driver until it is switched to manual
+    let controls = headtracker
+        .config
+        .camera_controls
+        .get(&headtracker.config.selected_camera)
+        .cloned()
+        .unwrap_or_default();
+
+    let mut controls_section = Column::new()
+        .spacing(5)
+        .push(text("Camera Controls").size(15))
+        .push(Space::with_height(Length::Fixed(15.)))
+        .push(
+            toggler(
+                "Auto exposure".to_string(),
+                controls.auto_exposure.unwrap_or(true),
+                Message::AutoExposure,
+            )
+            .size(20)
+            .text_size(14),
+        );
+
+    for control in CameraControl::ALL {
+        let value = controls.get(control);
+        controls_section = controls_section.push(
+            toggler(
+                format!("Manual {}", control.label().to_lowercase()),
+                value.is_some(),
+                move |manual| {
+                    Message::CameraControl(control, manual.then(|| control.default_value()))
+                },
+            )
+            .size(20)
+            .text_size(14),
+        );
+
+        if let Some(value) = value {
+            let (min, max) = control.range();
+            controls_section = controls_section.push(
+                Row::new()
+                    .spacing(10)
+                    .push(
+                        slider(min..=max, value, move |value| {
+                            Message::CameraControl(control, Some(value))
+                        })
+                        .step(1.),
+                    )
+                    .push(text(format!("{value}")).size(14).width(Length::Fixed(50.))),
+            );
+        }
+    }
+    controls_section = controls_section
+        .push(button(text("Driver defaults").size(14)).on_press(Message::DefaultCameraControls));
+
+    // Landmark models found in the models directory, each folder has a manifest describing it
+    let model_section = Column::new()
+        .push(text("Models").size(15))
+        .push(Space::with_height(Length::Fixed(20.)))
+        .push(
+            Row::new()
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .push(
+                    pick_list(
+                        Cow::from(headtracker.model_list.clone()),
+                        Some(headtracker.config.model.clone()),
+                        Message::Model,
+                    )
+                    .width(Length::FillPortion(70)),
+                )
+                .push(button(text("Open models folder").size(14)).on_press(Message::OpenModels)),
+        );
+
+    // Which face is tracked when there are several people or a poster in the frame
+    let face_section = Column::new()
+        .push(text("Face Selection").size(15))
+        .push(Space::with_height(Length::Fixed(20.)))
+        .push(
+            pick_list(
+                Cow::from(FacePolicy::ALL.to_vec()),
+                Some(headtracker.config.face_policy),
+                Message::FacePolicy,
+            )
+            .width(Length::Fill),
+        )
+        .push(Space::with_height(Length::Fixed(10.)))
+        .push(
+            toggler(
+                "Only reacquire the same person after losing track".to_string(),
+                headtracker.config.reidentify,
+                Message::Reidentify,
+            )
+            .size(20)
+            .text_size(14),
+        )
+        .push(Space::with_height(Length::Fixed(10.)))
+        .push(
+            text(format!(
+                "Detection confidence : {:.2}",
+                headtracker
+                    .config
+                    .detection_threshold
+                    .load(Ordering::SeqCst)
+            ))
+            .size(14),
+        )
+        .push(
+            slider(
+                0.05..=0.95,
+                headtracker
+                    .config
+                    .detection_threshold
+                    .load(Ordering::SeqCst),
+                Message::DetectionThreshold,
+            )
+            .step(0.05),
+        )
+        .push(
+            text(format!(
+                "Overlap between detections of the same face : {:.2}",
+                headtracker.config.nms_threshold.load(Ordering::SeqCst)
+            ))
+            .size(14),
+        )
+        .push(
+            slider(
+                0.05..=0.95,
+                headtracker.config.nms_threshold.load(Ordering::SeqCst),
+                Message::NmsThreshold,
+            )
+            .step(0.05),
+        )
+        .push(
+            text(format!(
+                "Run the face detector every {} frames (and whenever the face is lost)",
+                headtracker.config.detection_interval.load(Ordering::SeqCst)
+            ))
+            .size(14),
+        )
+        .push(
+            slider(
+                1..=30,
+                headtracker.config.detection_interval.load(Ordering::SeqCst),
+                Message::DetectionIntervalChanged,
+            )
+            .step(1u32),
+        )
+        .push(
+            text(format!(
+                "Drop frames with a landmark quality below {:.2} (hands in front of the face, blur)",
+                headtracker.config.min_quality.load(Ordering::SeqCst)
+            ))
+            .size(14),
+        )
+        .push(
+            slider(
+                0.0..=0.9,
+                headtracker.config.min_quality.load(Ordering::SeqCst),
+                Message::MinQuality,
+            )
+            .step(0.05),
+        );
+
+    // What opentrack receives when the face can't be found
+    let loss_timeout = headtracker.config.loss_timeout.load(Ordering::SeqCst);
+    let mut loss_section = Column::new()
+        .push(text("When the Face Is Lost").size(15))
+        .push(Space::with_height(Length::Fixed(20.)))
+        .push(
+            pick_list(
+                Cow::from(LossPolicy::ALL.to_vec()),
+                Some(headtracker.config.loss_policy),
+                Message::LossPolicy,
+            )
+            .width(Length::Fill),
+        );
+    if headtracker.config.loss_policy == LossPolicy::HoldThenCenter {
+        loss_section = loss_section
+            .push(Space::with_height(Length::Fixed(10.)))
+            .push(
+                text(format!(
+                    "Going back to the center after {loss_timeout:.1} seconds"
+                ))
+                .size(14),
+            )
+            .push(slider(0.0..=10.0, loss_timeout, Message::LossTimeout).step(0.5));
+    }
+
+    // Applied to the frames before tracking, for cameras mounted sideways or upside down
+    let frame_transform = headtracker.config.frame_transform;
+    let orientation_section = Column::new()
+        .push(text("Orientation").size(15))
+        .push(Space::with_height(Length::Fixed(20.)))
+        .push(
+            pick_list(
+                Cow::from(Rotation::ALL.to_vec()),
+                Some(frame_transform.rotation),
+                Message::Rotation,
+            )
+            .width(Length::Fill),
+        )
+        .push(Space::with_height(Length::Fixed(10.)))
+        .push(
+            toggler(
+                "Mirror horizontally".to_string(),
+                frame_transform.mirror,
+                Message::Mirror,
+            )
+            .size(20)
+            .text_size(14),
+        )
+        .push(
+            toggler(
+                "Flip vertically".to_string(),
+                frame_transform.flip,
+                Message::Flip,
+            )
+            .size(20)
+            .text_size(14),
+        );
+
+    // Region of the frame the face is searched in, outlined on the preview
+    let capture_roi = *headtracker.config.capture_roi.lock().unwrap();
+    let mut roi_section = Column::new()
+        .push(text("Region of Interest").size(15))
+        .push(Space::with_height(Length::Fixed(20.)))
+        .push(
+            text("Zooms on part of the frame, for wide angle cameras where the face is small")
+                .size(14),
+        )
+        .push(
+            toggler(
+                "Crop to a region of the frame".to_string(),
+                capture_roi.is_some(),
+                |enabled| Message::CaptureRoi(enabled.then(CaptureRoi::full)),
+            )
+            .size(20)
+            .text_size(14),
+        );
+
+    if let Some(roi) = capture_roi {
+        let roi_sliders: [(&str, f32, fn(CaptureRoi, f32) -> CaptureRoi); 4] = [
+            ("Left", roi.x, |roi, x| CaptureRoi { x, ..roi }),
+            ("Top", roi.y, |roi, y| CaptureRoi { y, ..roi }),
+            ("Width", roi.width, |roi, width| CaptureRoi { width, ..roi }),
+            ("Height", roi.height, |roi, height| CaptureRoi {
+                height,
+                ..roi
+            }),
+        ];
+        for (label, value, update) in roi_sliders {
+            roi_section = roi_section.push(
+                Row::new()
+                    .spacing(10)
+                    .push(text(label).size(14).width(Length::Fixed(60.)))
+                    .push(
+                        slider(0.0..=1.0, value, move |value| {
+                            Message::CaptureRoi(Some(update(roi, value)))
+                        })
+                        .step(0.01),
+                    )
+                    .push(
+                        text(format!("{:.0}%", value * 100.))
+                            .size(14)
+                            .width(Length::Fixed(50.)),
+                    ),
+            );
+        }
+    }
+
+    // Annotated preview served over HTTP, for browsers and OBS browser sources on this or another machine
+    let preview_port = headtracker.config.preview_port.as_str();
+    let preview_host = match headtracker.config.preview_lan {
+        true => "<this computer's IP>",
+        false => "127.0.0.1",
+    };
+    let preview_section = Column::new()
+        .push(text("Preview Stream").size(15))
+        .push(Space::with_height(Length::Fixed(20.)))
+        .push(
+            toggler(
+                "Stream the preview with the landmarks over HTTP (MJPEG)".to_string(),
+                headtracker.config.preview_server,
+                Message::PreviewServer,
+            )
+            .size(20)
+            .text_size(14),
+        )
+        .push(Space::with_height(Length::Fixed(10.)))
+        .push(
+            Row::new()
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .push(text("Port").size(14))
+                .push(
+                    text_input("9090", preview_port)
+                        .on_input(Message::PreviewPort)
+                        .width(Length::Fixed(80.)),
+                )
+                .push(
+                    text(format!(
+                        "Open http://{}:{}/ once tracking is started",
+                        preview_host,
+                        preview_port.trim()
+                    ))
+                    .size(14),
+                ),
+        )
+        .push(Space::with_height(Length::Fixed(10.)))
+        // * Anyone on the network can then watch the camera, so it is off by default
+        .push(
+            toggler(
+                "Allow other devices on the network to watch the preview".to_string(),
+                headtracker.config.preview_lan,
+                Message::PreviewLan,
+            )
+            .size(20)
+            .text_size(14),
+        );
+
+    // Frames dropped are the ones replaced by a newer frame before the tracker could process them
+    let diagnostics_section = Column::new()
+        .push(text("Diagnostics").size(15))
+        .push(Space::with_height(Length::Fixed(20.)))
+        .push(text(format!("Frames : {}", headtracker.frames.stats)).size(14))
+        .push(Space::with_height(Length::Fixed(10.)))
+        // * Frames and poses to attach to bug reports, the video can be replayed as the input
+        .push(
+            Row::new()
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .push(
+                    toggler(
+                        "Record session".to_string(),
+                        headtracker.recording.load(Ordering::SeqCst),
+                        Message::RecordSession,
+                    )
+                    .size(20)
+                    .text_size(14)
+                    .width(Length::FillPortion(70)),
+                )
+                .push(button(text("Open recordings").size(14)).on_press(Message::OpenRecordings)),
+        );
+
+    let settings = Column::new()
+        .push(input_section)
+        .push(Space::with_height(Length::Fixed(30.)))
+        .push(capture_section)
+        .push(Space::with_height(Length::Fixed(30.)))
+        .push(controls_section)
+        .push(Space::with_height(Length::Fixed(30.)))
+        .push(model_section)
+        .push(Space::with_height(Length::Fixed(30.)))
+        .push(face_section)
+        .push(Space::with_height(Length::Fixed(30.)))
+        .push(loss_section)
+        .push(Space::with_height(Length::Fixed(30.)))
+        .push(orientation_section)
+        .push(Space::with_height(Length::Fixed(30.)))
+        .push(roi_section)
+        .push(Space::with_height(Length::Fixed(30.)))
+        .push(preview_section)
+        .push(Space::with_height(Length::Fixed(30.)))
+        .push(diagnostics_section);
+
+    let body = Container::new(
+        Column::new()
+            .width(Length::Fill)
+            .push(Space::with_height(Length::Fixed(40.)))
+            .push(Container::new(
+                Row::new()
+                    .push(Space::with_width(Length::Fill))
+                    .push(button(text("  Back  ").size(15)).on_press(Message::ToggleAdvanced))
+                    .push(Space::with_width(Length::Fixed(40.))),
+            ))
+            .push(scrollable(Container::new(settings).padding(40)).height(Length::Fill))
+            .push(
+                Container::new(
+                    text(headtracker.error_tracker.clone().lock().unwrap())
+                        .size(15)
+                        .horizontal_alignment(Horizontal::Center),
+                )
+                .width(Length::Fill)
+                .center_x(),
+            ),
+    )
+    .height(Length::FillPortion(HEIGHT_BODY));
+
+    Column::new().spacing(10).push(body).push(footer())
+}
+
+// Outlines the region of interest, and the faces when the face to track is picked by hand
+fn annotate_preview(frame: &mut Mat, headtracker: &HeadTracker) -> opencv::Result<()> {
+    let frame_size = frame.size()?;
+
+    if let Some(capture_roi) = *headtracker.config.capture_roi.lock().unwrap() {
+        imgproc::rectangle(
+            frame,
+            capture_roi.to_rect(frame_size),
+            Scalar::new(0., 255., 0., 0.),
+            2,
+            imgproc::LINE_8,
+            0,
+        )?;
+    }
+
+    if headtracker.config.face_policy == FacePolicy::Manual {
+        let (width, height) = (frame_size.width as f32, frame_size.height as f32);
+        let faces = headtracker.detected_faces.lock().unwrap().clone();
+        for [left, top, right, bottom] in faces {
+            let rect = Rect::new(
+                (left * width) as i32,
+                (top * height) as i32,
+                ((right - left) * width) as i32,
+                ((bottom - top) * height) as i32,
+            );
+            imgproc::rectangle(
+                frame,
+                rect,
+                Scalar::new(255., 128., 0., 0.),
+                2,
+                imgproc::LINE_8,
+                0,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
 // Shows app version and links to github and logs
 fn footer() -> Container<'static, Message, iced::Theme, Renderer> {
     let github_button = button(
@@ -0,0 +1,197 @@
+/// Handing the latest frame from the capture thread over to the tracker and the preview
+use crate::structs::{
+    frame::Frame,
+    mailbox::{FrameMailbox, FrameStats, FrameSubscription, MailboxSlot},
+};
+
+use std::{
+    sync::{atomic::Ordering, Arc, Condvar, Mutex},
+    time::Duration,
+};
+
+impl FrameMailbox {
+    pub fn new() -> Self {
+        Self {
+            slot: Arc::new((
+                Mutex::new(MailboxSlot {
+                    frame: None,
+                    seq: 0,
+                }),
+                Condvar::new(),
+            )),
+            stats: Arc::new(FrameStats::default()),
+        }
+    }
+
+    // Replaces the frame in the slot and numbers it, called by the capture thread
+    pub fn post(&self, mut frame: Frame) {
+        let (slot, new_frame) = &*self.slot;
+        {
+            let mut slot = slot.lock().unwrap();
+            slot.seq += 1;
+            frame.seq = slot.seq;
+            slot.frame = Some(frame);
+        }
+        self.stats.captured.fetch_add(1, Ordering::SeqCst);
+        new_frame.notify_all();
+    }
+
+    // Subscription used by the tracking thread, counts processed and dropped frames
+    pub fn subscribe_tracker(&self) -> FrameSubscription {
+        self.subscribe(true)
+    }
+
+    // Subscription used by the GUI to show the camera, doesn't affect the counters
+    pub fn subscribe_preview(&self) -> FrameSubscription {
+        self.subscribe(false)
+    }
+
+    fn subscribe(&self, count_frames: bool) -> FrameSubscription {
+        // * Starting from the current frame, so that a frame left over from a previous run isn't picked up
+        let last_seq = self.slot.0.lock().unwrap().seq;
+        FrameSubscription {
+            mailbox: self.clone(),
+            last_seq,
+            count_frames,
+        }
+    }
+}
+
+impl Default for FrameMailbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameStats {
+    pub fn reset(&self) {
+        self.captured.store(0, Ordering::SeqCst);
+        self.processed.store(0, Ordering::SeqCst);
+        self.dropped.store(0, Ordering::SeqCst);
+        self.sent.store(0, Ordering::SeqCst);
+        self.last_latency_us.store(0, Ordering::SeqCst);
+        self.total_latency_us.store(0, Ordering::SeqCst);
+    }
+
+    // Called once a pose was sent, with the time elapsed since its frame was captured
+    pub fn record_latency(&self, latency: Duration) {
+        let latency_us = latency.as_micros() as u64;
+        self.sent.fetch_add(1, Ordering::SeqCst);
+        self.last_latency_us.store(latency_us, Ordering::SeqCst);
+        self.total_latency_us
+            .fetch_add(latency_us, Ordering::SeqCst);
+    }
+
+    pub fn average_latency(&self) -> Duration {
+        let sent = self.sent.load(Ordering::SeqCst);
+        if sent == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_micros(self.total_latency_us.load(Ordering::SeqCst) / sent)
+    }
+}
+
+impl std::fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} captured, {} processed, {} dropped, latency {:.1} ms (avg {:.1} ms)",
+            self.captured.load(Ordering::SeqCst),
+            self.processed.load(Ordering::SeqCst),
+            self.dropped.load(Ordering::SeqCst),
+            self.last_latency_us.load(Ordering::SeqCst) as f64 / 1000.,
+            self.average_latency().as_secs_f64() * 1000.
+        )
+    }
+}
+
+impl FrameSubscription {
+    // Takes the latest frame if it wasn't seen yet by this subscription
+    pub fn try_latest(&mut self) -> Option<Frame> {
+        let slot = self.mailbox.slot.0.lock().unwrap();
+        self.take(&slot)
+    }
+
+    // Waits for a frame newer than the last one seen, returns None on timeout
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<Frame> {
+        let (slot, new_frame) = &*self.mailbox.slot;
+        let slot = slot.lock().unwrap();
+        let (slot, _) = new_frame
+            .wait_timeout_while(slot, timeout, |slot| slot.seq <= self.last_seq)
+            .unwrap();
+        self.take(&slot)
+    }
+
+    fn take(&mut self, slot: &MailboxSlot) -> Option<Frame> {
+        if slot.seq <= self.last_seq {
+            return None;
+        }
+        let frame = slot.frame.clone()?;
+
+        if self.count_frames {
+            let stats = &self.mailbox.stats;
+            stats.processed.fetch_add(1, Ordering::SeqCst);
+            stats
+                .dropped
+                .fetch_add(slot.seq - self.last_seq - 1, Ordering::SeqCst);
+        }
+        self.last_seq = slot.seq;
+
+        Some(frame)
+    }
+}
+
+#[test]
+pub fn test_frame_mailbox() {
+    use opencv::{
+        core::{Scalar, Vec3b, CV_8UC3},
+        prelude::{Mat, MatTraitConst},
+    };
+
+    let frame = |value: f64| {
+        Frame::new(Mat::new_rows_cols_with_default(4, 4, CV_8UC3, Scalar::all(value)).unwrap())
+    };
+
+    let mailbox = FrameMailbox::new();
+    mailbox.post(frame(1.));
+
+    let mut tracker = mailbox.subscribe_tracker();
+    let mut preview = mailbox.subscribe_preview();
+
+    // Frames posted before subscribing are ignored
+    assert!(tracker.try_latest().is_none());
+    assert!(tracker.recv_timeout(Duration::from_millis(10)).is_none());
+
+    // Both consumers get the latest frame, without stealing it from each other
+    mailbox.post(frame(2.));
+    mailbox.post(frame(3.));
+    mailbox.post(frame(4.));
+    let latest = tracker.recv_timeout(Duration::from_millis(10)).unwrap();
+    assert_eq!(
+        *latest.mat.at_2d::<Vec3b>(0, 0).unwrap(),
+        Vec3b::from([4, 4, 4])
+    );
+    assert_eq!(latest.seq, 4);
+    assert_eq!(preview.try_latest().map(|frame| frame.seq), Some(4));
+
+    // A frame is only handed out once to each consumer
+    assert!(tracker.try_latest().is_none());
+    assert!(preview.try_latest().is_none());
+
+    // Waking up on a frame posted from another thread
+    let poster = mailbox.clone();
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        poster.post(frame(5.));
+    });
+    let next = tracker.recv_timeout(Duration::from_secs(5)).unwrap();
+    handle.join().unwrap();
+    assert_eq!(next.seq, 5);
+    assert!(next.captured_at > latest.captured_at);
+
+    // 5 frames captured, the first one before subscribing, 2 replaced before the tracker got to them
+    let stats = &mailbox.stats;
+    assert_eq!(stats.captured.load(Ordering::SeqCst), 5);
+    assert_eq!(stats.processed.load(Ordering::SeqCst), 2);
+    assert_eq!(stats.dropped.load(Ordering::SeqCst), 2);
+}
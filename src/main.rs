@@ -6,11 +6,19 @@ mod consts;
 mod enums;
 mod face;
 mod filter;
+mod frame;
 mod gui;
+mod mailbox;
+mod model;
 mod network;
+mod preview_server;
 mod process;
+mod recorder;
+mod signature;
+mod source;
 mod structs;
 mod tddfa;
+mod tracking;
 mod utils;
 
 use crate::{
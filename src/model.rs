@@ -0,0 +1,189 @@
+/// Models the tracker runs, the bundled ones or others dropped in the models directory
+use crate::{
+    consts::{APP_NAME, BLAZE_FACE_MODEL, BUILTIN_MODEL_NAME, DATA, MODEL, MODEL_MANIFEST_FILE},
+    structs::{
+        data::Jsondata,
+        model::{ModelManifest, ModelSet},
+        tddfa::ParamLayout,
+    },
+};
+
+use anyhow::{bail, Context, Result};
+use std::{
+    borrow::Cow,
+    fs,
+    path::{Path, PathBuf},
+};
+
+// Checks the parameter statistics and bases match the number of parameters of the model
+fn check_data(data: &Jsondata, param_count: usize) -> Result<()> {
+    let layout = ParamLayout::from_data(data)?;
+    if layout.param_count() != param_count {
+        bail!(
+            "The data file is for {} parameters instead of {param_count}",
+            layout.param_count()
+        );
+    }
+    Ok(())
+}
+
+impl ModelSet {
+    // Each folder of this directory holds a model along with its manifest
+    pub fn models_dir() -> Result<PathBuf> {
+        let dirs = directories::ProjectDirs::from("rs", "", APP_NAME)
+            .context("Unable to find the data directory")?;
+        Ok(dirs.data_dir().join("models"))
+    }
+
+    // Models embedded in the binary
+    pub fn builtin() -> Result<Self> {
+        Ok(Self {
+            name: BUILTIN_MODEL_NAME.to_owned(),
+            input_size: 120,
+            landmark_model: Cow::Borrowed(MODEL),
+            data: serde_json::from_slice(DATA)?,
+            face_detector: Cow::Borrowed(BLAZE_FACE_MODEL),
+        })
+    }
+
+    // Names of the models that can be picked, the bundled one first
+    pub fn available() -> Vec<String> {
+        let mut names = match Self::models_dir().and_then(|directory| Ok(fs::read_dir(directory)?))
+        {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().join(MODEL_MANIFEST_FILE).is_file())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .collect(),
+            Err(_) => vec![],
+        };
+        names.sort();
+        names.insert(0, BUILTIN_MODEL_NAME.to_owned());
+        names
+    }
+
+    // Model picked in the settings, the bundled one when none is picked
+    // * A model that was removed or renamed falls back to the bundled one, only broken models are errors
+    pub fn load(name: &str) -> Result<Self> {
+        if name.is_empty() || name == BUILTIN_MODEL_NAME {
+            return Self::builtin();
+        }
+
+        let directory = Self::models_dir()?.join(name);
+        if !directory.join(MODEL_MANIFEST_FILE).is_file() {
+            tracing::warn!("The model {name} wasn't found, using the built-in one");
+            return Self::builtin();
+        }
+        Self::load_from(&directory)
+    }
+
+    // * Everything is checked here, so a broken model is reported before tracking starts
+    pub fn load_from(directory: &Path) -> Result<Self> {
+        let name = directory
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let manifest_path = directory.join(MODEL_MANIFEST_FILE);
+        let manifest = fs::read(&manifest_path)
+            .with_context(|| format!("Unable to read {}", manifest_path.display()))?;
+        let manifest: ModelManifest = serde_json::from_slice(&manifest)
+            .with_context(|| format!("Invalid manifest for the model {name}"))?;
+        if !(1..=1024).contains(&manifest.input_size) {
+            bail!(
+                "Invalid input size {} for the model {name}",
+                manifest.input_size
+            );
+        }
+
+        // Reading a file of the model, making sure it isn't corrupted
+        let read = |file: &str| -> Result<Vec<u8>> {
+            let path = directory.join(file);
+            let bytes =
+                fs::read(&path).with_context(|| format!("Unable to read {}", path.display()))?;
+            if let Some(expected) = manifest.checksums.get(file) {
+                let checksum = format!("{:08x}", crc32fast::hash(&bytes));
+                if !checksum.eq_ignore_ascii_case(expected.trim()) {
+                    bail!("{file} of the model {name} is corrupted, its checksum is {checksum} instead of {expected}");
+                }
+            }
+            Ok(bytes)
+        };
+
+        let landmark_model = read(&manifest.landmark_model)?;
+        let data: Jsondata = serde_json::from_slice(&read(&manifest.data)?)
+            .with_context(|| format!("Invalid data file for the model {name}"))?;
+        check_data(&data, manifest.param_count)
+            .with_context(|| format!("Invalid data file for the model {name}"))?;
+        let face_detector = match &manifest.face_detector {
+            Some(file) => Cow::Owned(read(file)?),
+            None => Cow::Borrowed(BLAZE_FACE_MODEL),
+        };
+
+        Ok(Self {
+            name,
+            input_size: manifest.input_size,
+            landmark_model: Cow::Owned(landmark_model),
+            data,
+            face_detector,
+        })
+    }
+}
+
+#[test]
+pub fn test_model_registry() -> Result<()> {
+    use std::collections::HashMap;
+
+    let directory = std::env::temp_dir().join(format!("stableview-model-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    fs::write(directory.join("model.onnx"), MODEL)?;
+    fs::write(directory.join("data.json"), DATA)?;
+
+    let mut manifest = ModelManifest {
+        input_size: 120,
+        param_count: 62,
+        landmark_model: "model.onnx".to_owned(),
+        data: "data.json".to_owned(),
+        face_detector: None,
+        checksums: HashMap::from([(
+            "model.onnx".to_owned(),
+            format!("{:08x}", crc32fast::hash(MODEL)),
+        )]),
+    };
+    let write_manifest = |manifest: &ModelManifest| -> std::io::Result<()> {
+        fs::write(
+            directory.join(MODEL_MANIFEST_FILE),
+            serde_json::to_vec(manifest)?,
+        )
+    };
+
+    write_manifest(&manifest)?;
+    let models = ModelSet::load_from(&directory)?;
+    assert_eq!(models.input_size, 120);
+    assert_eq!(models.landmark_model.len(), MODEL.len());
+
+    // Corrupted files and data that doesn't match the model are reported
+    manifest
+        .checksums
+        .insert("model.onnx".to_owned(), "00000000".to_owned());
+    write_manifest(&manifest)?;
+    assert!(ModelSet::load_from(&directory).is_err());
+
+    manifest.checksums.clear();
+    manifest.param_count = 72;
+    write_manifest(&manifest)?;
+    assert!(ModelSet::load_from(&directory).is_err());
+
+    manifest.param_count = 62;
+    manifest.data = "missing.json".to_owned();
+    write_manifest(&manifest)?;
+    assert!(ModelSet::load_from(&directory).is_err());
+
+    fs::remove_dir_all(&directory)?;
+
+    // Models that aren't there anymore fall back to the bundled one
+    let models = ModelSet::load(&format!("missing-model-{}", std::process::id()))?;
+    assert_eq!(models.name, BUILTIN_MODEL_NAME);
+
+    Ok(())
+}
@@ -1,7 +1,7 @@
 /// Deals with sending the data (x,y,depth,yaw,pitch,roll) to opentrack (https://github.com/opentrack/opentrack) using UDP socket
-use crate::structs::network::SocketNetwork;
+use crate::structs::{frame::PoseSample, network::SocketNetwork};
 use anyhow::{Context, Result};
-use std::net::UdpSocket;
+use std::{net::UdpSocket, time::Duration};
 
 impl SocketNetwork {
     pub fn new(ip: String, port: String) -> Result<Self> {
@@ -19,7 +19,9 @@ impl SocketNetwork {
     }
 
     // TODO : Cleaning and possibly removing unsafe code
-    pub fn send(&mut self, data: [f32; 6]) -> Result<()> {
+    // Returns the end-to-end latency, from the capture of the frame to the pose being sent
+    pub fn send(&mut self, sample: &PoseSample) -> Result<Duration> {
+        let data = sample.data;
         let data: [f64; 6] = [
             data[0] as f64,
             data[1] as f64,
@@ -36,14 +38,19 @@ impl SocketNetwork {
         // Send data
         self.socket_network.send_to(out, &self.address)?;
 
-        Ok(())
+        Ok(sample.latency())
     }
 }
 
 #[test]
 pub fn test_socket_network() -> Result<()> {
     let mut socket_network = SocketNetwork::new("127.0.0.1".to_owned(), "4242".to_owned())?;
-    socket_network.send([1., 2., 3., 4., 5., 6.])?;
+    let sample = PoseSample {
+        data: [1., 2., 3., 4., 5., 6.],
+        seq: 1,
+        captured_at: std::time::Instant::now(),
+    };
+    socket_network.send(&sample)?;
 
     Ok(())
 }
@@ -0,0 +1,244 @@
+/// Streaming the annotated preview over HTTP, as multipart JPEGs (MJPEG)
+use crate::{
+    consts::{PREVIEW_CLIENT_TIMEOUT, PREVIEW_JPEG_QUALITY},
+    structs::preview_server::{PreviewServer, PreviewSlot},
+};
+
+use anyhow::{Context, Result};
+use opencv::{
+    core::{Vector, VectorToVec},
+    imgcodecs,
+    prelude::Mat,
+};
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+// Boundary between the JPEG parts of the stream
+const BOUNDARY: &str = "stableview";
+
+impl PreviewServer {
+    // Only listens on the loopback interface, unless other machines on the network are allowed to watch
+    pub fn start(port: u16, lan: bool) -> Result<Self> {
+        let host = match lan {
+            true => "0.0.0.0",
+            false => "127.0.0.1",
+        };
+        let listener = TcpListener::bind((host, port))
+            .with_context(|| format!("Unable to serve the preview on port {port}"))?;
+        let address = listener.local_addr()?;
+        // * Polling for connections, so that shutting down doesn't wait for a client
+        listener.set_nonblocking(true)?;
+
+        let slot = Arc::new((
+            Mutex::new(PreviewSlot {
+                jpeg: Arc::new(vec![]),
+                seq: 0,
+            }),
+            Condvar::new(),
+        ));
+        let clients = Arc::new(AtomicUsize::new(0));
+        let keep_running = Arc::new(AtomicBool::new(true));
+
+        let (cloned_slot, cloned_clients, cloned_keep_running) =
+            (slot.clone(), clients.clone(), keep_running.clone());
+        let server_thread = Some(thread::spawn(move || {
+            while cloned_keep_running.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok((stream, peer)) => {
+                        tracing::info!("Preview client {} connected", peer);
+                        let (slot, clients, keep_running) = (
+                            cloned_slot.clone(),
+                            cloned_clients.clone(),
+                            cloned_keep_running.clone(),
+                        );
+                        thread::spawn(move || {
+                            clients.fetch_add(1, Ordering::SeqCst);
+                            if let Err(error) = serve_client(stream, &slot, &keep_running) {
+                                tracing::info!("Preview client {} disconnected : {}", peer, error);
+                            }
+                            clients.fetch_sub(1, Ordering::SeqCst);
+                        });
+                    }
+                    Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(50));
+                    }
+                    Err(error) => tracing::error!("Unable to accept a preview client : {}", error),
+                }
+            }
+        }));
+
+        tracing::info!("Serving the preview on http://{}/", address);
+
+        Ok(Self {
+            address,
+            slot,
+            clients,
+            keep_running,
+            server_thread,
+        })
+    }
+
+    // Whether a client is connected, the frames don't need to be annotated otherwise
+    pub fn is_watched(&self) -> bool {
+        self.clients.load(Ordering::SeqCst) > 0
+    }
+
+    // Encodes the frame once for all the clients, nothing is encoded if nobody is watching
+    pub fn publish(&self, frame: &Mat) -> Result<()> {
+        if !self.is_watched() {
+            return Ok(());
+        }
+
+        let mut jpeg = Vector::<u8>::new();
+        imgcodecs::imencode(
+            ".jpg",
+            frame,
+            &mut jpeg,
+            &Vector::from_slice(&[imgcodecs::IMWRITE_JPEG_QUALITY, PREVIEW_JPEG_QUALITY]),
+        )?;
+
+        let (slot, new_frame) = &*self.slot;
+        {
+            let mut slot = slot.lock().unwrap();
+            slot.jpeg = Arc::new(jpeg.to_vec());
+            slot.seq += 1;
+        }
+        new_frame.notify_all();
+        Ok(())
+    }
+
+    pub fn shutdown(&mut self) {
+        self.keep_running.store(false, Ordering::SeqCst);
+        // Waking up the clients waiting for a frame
+        self.slot.1.notify_all();
+
+        if let Some(server_thread) = self.server_thread.take() {
+            if let Err(error) = server_thread.join() {
+                tracing::error!("Unable to join preview server thread : {:?}", error);
+            }
+        }
+    }
+}
+
+// Frees the port when tracking stops early, e.g. when the model can't be loaded
+impl Drop for PreviewServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+// Sends every new frame to the client until it disconnects or the server shuts down
+fn serve_client(
+    stream: TcpStream,
+    slot: &(Mutex<PreviewSlot>, Condvar),
+    keep_running: &AtomicBool,
+) -> Result<()> {
+    // The request itself doesn't matter, every path serves the stream
+    stream.set_nonblocking(false)?;
+    // * Without timeouts a stalled client would keep its thread blocked, shutting down doesn't join them
+    stream.set_read_timeout(Some(PREVIEW_CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(PREVIEW_CLIENT_TIMEOUT))?;
+    let mut request = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    while request.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.0 200 OK\r\n\
+         Cache-Control: no-cache\r\n\
+         Content-Type: multipart/x-mixed-replace; boundary={BOUNDARY}\r\n\r\n"
+    )?;
+
+    let (slot, new_frame) = slot;
+    let mut last_seq = 0;
+    while keep_running.load(Ordering::SeqCst) {
+        let jpeg = {
+            let guard = slot.lock().unwrap();
+            let (guard, _) = new_frame
+                .wait_timeout_while(guard, Duration::from_millis(500), |slot| {
+                    slot.seq == last_seq && keep_running.load(Ordering::SeqCst)
+                })
+                .unwrap();
+            if guard.seq == last_seq {
+                continue;
+            }
+            last_seq = guard.seq;
+            guard.jpeg.clone()
+        };
+
+        write!(
+            stream,
+            "--{BOUNDARY}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+            jpeg.len()
+        )?;
+        stream.write_all(&jpeg)?;
+        write!(stream, "\r\n")?;
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn test_preview_server() -> Result<()> {
+    use crate::{
+        enums::synthetic_pattern::SyntheticPattern,
+        source::FrameSource,
+        structs::source::{MjpegSource, SyntheticSource},
+    };
+    use opencv::prelude::MatTraitConst;
+
+    let server = PreviewServer::start(0, false)?;
+    assert!(server.address.ip().is_loopback());
+    let url = format!("http://127.0.0.1:{}/", server.address.port());
+
+    let mut frame = Mat::default();
+    SyntheticSource::new(SyntheticPattern::MovingRectangle, 160, 120, None).read(&mut frame)?;
+
+    // Nothing is encoded until a client connects
+    server.publish(&frame)?;
+    assert_eq!(server.slot.0.lock().unwrap().seq, 0);
+
+    // * The client is counted before the response headers are sent, so it is known once the stream is open
+    let mut client = MjpegSource::open(&url)?;
+    assert_eq!(server.clients.load(Ordering::SeqCst), 1);
+    server.publish(&frame)?;
+
+    let mut received = Mat::default();
+    assert!(client.read(&mut received)?);
+    assert_eq!(received.size()?, frame.size()?);
+
+    drop(client);
+
+    // A client that never sends its request is dropped after the timeout
+    // * The client dropped above is only noticed on the next write, so it may still be counted
+    let clients = server.clients.load(Ordering::SeqCst);
+    let _stalled = TcpStream::connect(("127.0.0.1", server.address.port()))?;
+    let deadline = std::time::Instant::now() + PREVIEW_CLIENT_TIMEOUT * 2;
+    let wait_for = |count: usize| {
+        while server.clients.load(Ordering::SeqCst) != count && std::time::Instant::now() < deadline
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        server.clients.load(Ordering::SeqCst)
+    };
+    assert_eq!(wait_for(clients + 1), clients + 1);
+    assert_eq!(wait_for(clients), clients);
+
+    // Dropping the server frees its port
+    let port = server.address.port();
+    drop(server);
+    PreviewServer::start(port, false)?;
+
+    Ok(())
+}
@@ -1,17 +1,29 @@
 
 /// Processing the head pose (filters, etc.) and generating the x,y,z of the head.
-use crate::enums::crop_policy::CropPolicy;
+use crate::enums::{
+    crop_policy::CropPolicy, face_policy::FacePolicy, tracking_state::TrackingState,
+};
+use crate::consts::{QUALITY_MAX_JUMP, QUALITY_MAX_REJECTED_FRAMES, QUALITY_MIN_IOU, REID_TIMEOUT};
+use crate::face;
 use crate::structs::face::FaceDetect;
+use crate::structs::frame::{Frame, PoseSample};
+use crate::structs::model::ModelSet;
+use crate::structs::signature::FaceSignature;
 use crate::structs::{pose::ProcessHeadPose, tddfa::Tddfa};
 use crate::utils::headpose::{calc_pose, gen_point2d};
 use anyhow::{Context, Result};
-use opencv::prelude::Mat;
-use opencv::prelude::MatTraitConst;
+use opencv::{
+    core::{Mat, Rect, Size},
+    prelude::MatTraitConst,
+};
+use std::time::{Duration, Instant};
 
 impl ProcessHeadPose {
-    pub fn new(image_size: i32) -> Result<Self> {
-        let tddfa = Tddfa::new(image_size).context("Unable to create tddfa")?;
-        let face_detector = FaceDetect::new().unwrap();
+    pub fn new(models: &ModelSet) -> Result<Self> {
+        let tddfa = Tddfa::new(models).context("Unable to create tddfa")?;
+        let param_count = tddfa.layout.param_count();
+        let face_detector =
+            FaceDetect::new(&models.face_detector).context("Unable to create the face detector")?;
 
         Ok(Self {
             tddfa,
@@ -19,18 +31,35 @@ impl ProcessHeadPose {
             pts_3d: vec![vec![1., 2., 3.], vec![4., 5., 6.], vec![7., 8., 9.]],
             face_box: [150., 150., 400., 400.],
             first_iteration: true,
-            param: [0.; 62],
+            param: vec![0.; param_count],
             roi_box: [150., 150., 400., 400.],
+            capture_roi: None,
+            face_policy: FacePolicy::default(),
+            detected_faces: vec![],
+            tracked_face: None,
+            manual_target: None,
+            reidentify: true,
+            signature: None,
+            tracked_at: None,
+            tracking_state: TrackingState::default(),
+            detection_interval: 1,
+            frames_since_detection: 0,
+            quality: 1.,
+            min_quality: 0.,
+            rejected_frames: 0,
+            last_pose: [0.; 6],
         })
     }
 
     // Get the X,Y,Z coordinates of the head
+    // * Relative to the center of the frame so rotated (portrait) frames keep the head centered
     fn get_coordintes_and_depth(
         &self,
         pose: [f32; 3],
         mut distance: f32,
         _point2d: Vec<Vec<f32>>,
         roi_box: &[f32; 4],
+        frame_size: Size,
     ) -> ([f32; 2], f32) {
         distance -= 56.;
         distance += (pose[0] * 0.2).abs();
@@ -41,8 +70,9 @@ impl ProcessHeadPose {
         let mut centroid = [
             // x.iter().sum::<f32>() / (x.len()) as f32,
             // y.iter().sum::<f32>() / (y.len()) as f32,
-            ((roi_box[2] + roi_box[0]) / 20.) - 40.,
-            ((roi_box[3] + roi_box[1]) / 20.) - 15.,
+            // * Offsets were tuned on 640x480 frames
+            (((roi_box[2] + roi_box[0]) / 2. - frame_size.width as f32 / 2.) / 10.) - 8.,
+            (((roi_box[3] + roi_box[1]) / 2. - frame_size.height as f32 / 2.) / 10.) + 9.,
         ];
         // * disbling the multiplying pose with distance (pose[0]*(distance/31), pose[1]*(distance/27)), it seems to causing jitting even when blinking eyes or smiling
         // centroid[0] += pose[0]; // * When very close to the camera, the head pose invariant seems to does't work, to miltgate the issue, we use this
@@ -56,21 +86,140 @@ impl ProcessHeadPose {
         (centroid, distance)
     }
 
-    pub fn single_iter(&mut self, frame: &Mat) -> Result<[f32; 6]> {
+    // Searching goes on until a face is found for the first time
+    fn lose_track(&mut self) {
+        if self.tracking_state == TrackingState::Tracking {
+            self.tracking_state = TrackingState::Lost;
+        }
+    }
+
+    // How much the landmarks of this frame can be trusted, the lowest of the parameter plausibility,
+    // the agreement with the tracked face box and the jump of the roi box
+    fn landmark_quality(&self, previous_roi_box: &[f32; 4], landmark_face: &face::Rect) -> f32 {
+        let plausibility = self.tddfa.param_plausibility(&self.param);
+
+        let agreement = match self.tracked_face {
+            Some(tracked_face) => (landmark_face.iou(&tracked_face) / QUALITY_MIN_IOU).min(1.),
+            None => 1.,
+        };
+
+        let center = |roi_box: &[f32; 4]| {
+            (
+                (roi_box[0] + roi_box[2]) / 2.,
+                (roi_box[1] + roi_box[3]) / 2.,
+            )
+        };
+        let ((x, y), (previous_x, previous_y)) = (center(&self.roi_box), center(previous_roi_box));
+        let width = (previous_roi_box[2] - previous_roi_box[0]).abs().max(1.);
+        let jump = (x - previous_x).hypot(y - previous_y) / width;
+        let steadiness = (1. - jump / QUALITY_MAX_JUMP).clamp(0., 1.);
+
+        plausibility.min(agreement).min(steadiness)
+    }
+
+    // Between two detections the landmarks drive the tracking, unless they jumped away from the face
+    // * The detector runs every detection_interval frames, and on every frame while the face isn't tracked
+    fn needs_detection(&self, landmarks_reliable: bool, landmark_face: &face::Rect) -> bool {
+        let tracked_face = match (self.tracking_state, self.tracked_face) {
+            (TrackingState::Tracking, Some(tracked_face)) => tracked_face,
+            _ => return true,
+        };
+        let (x, y) = landmark_face.center();
+        let landmarks_on_face = x >= tracked_face.x
+            && x <= tracked_face.right()
+            && y >= tracked_face.y
+            && y <= tracked_face.bottom();
+
+        !landmarks_reliable
+            || !landmarks_on_face
+            || self.frames_since_detection >= self.detection_interval
+    }
+
+    // * The detector sees the region of interest scaled to its input size, so faces far from the camera keep more detail
+    fn detect_faces(&mut self, frame: &Mat, search_area: Rect) -> Result<()> {
+        self.frames_since_detection = 0;
+        let (offset_x, offset_y) = (search_area.x as f32, search_area.y as f32);
+        self.detected_faces = self
+            .face_detector
+            .detect(Mat::roi(frame, search_area)?.try_clone()?)?
+            .into_iter()
+            .map(|face| face.translate(offset_x, offset_y))
+            .collect();
+        Ok(())
+    }
+
+    // Face to track among the detected ones, None while the tracked person isn't back
+    fn pick_face(&mut self, frame: &Mat, captured_at: Instant) -> Result<Option<face::Rect>> {
+        // Picking the face to track, so that someone walking behind the player doesn't take over
+        let face = match self.face_policy.select(
+            &self.detected_faces,
+            self.tracked_face,
+            self.manual_target,
+            frame.size()?,
+        ) {
+            Some(face) => face.rect,
+            None => return Ok(None),
+        };
+
+        // After losing the person, or when the face jumped elsewhere, only a face matching the tracked person is taken
+        let jumped = self
+            .tracked_face
+            .is_some_and(|tracked_face| face.iou(&tracked_face) <= 0.);
+        if let (true, true, Some(signature)) = (
+            self.reidentify,
+            self.tracking_state != TrackingState::Tracking || jumped,
+            &self.signature,
+        ) {
+            let matching_face =
+                signature.find_match(frame, &self.detected_faces, &mut self.tddfa, &self.pts_3d)?;
+            if matching_face.is_some() {
+                return Ok(matching_face);
+            }
+
+            // * The signature is dropped after a while, so that tracking can't stay lost for good
+            let lost_for = self.tracked_at.map_or(Duration::ZERO, |tracked_at| {
+                captured_at.saturating_duration_since(tracked_at)
+            });
+            if lost_for < REID_TIMEOUT {
+                return Ok(None);
+            }
+            tracing::warn!(
+                "The tracked person wasn't found for {:?}, taking any face",
+                lost_for
+            );
+            self.signature = None;
+        }
+
+        Ok(Some(face))
+    }
+
+    pub fn single_iter(&mut self, input: &Frame) -> Result<PoseSample> {
         // ! A very tuff bug laying around somewhere here, resulting in out of ordinary roi box values when moving to camera border
 
-        let mut return_data = [0.; 6];
+        let frame = &input.mat;
+        let return_data = [0.; 6];
+
+        // Part of the frame the face is searched in, coordinates are kept in full frame space
+        let frame_size = frame.size()?;
+        let search_area = match self.capture_roi {
+            Some(capture_roi) => capture_roi.to_rect(frame_size),
+            None => Rect::new(0, 0, frame_size.width, frame_size.height),
+        };
+
+        // Whether the landmarks can follow the face on their own, without the face detector
+        let mut landmarks_reliable = true;
+        let previous = (self.param.clone(), self.roi_box, self.pts_3d.clone());
 
         if self.first_iteration {
             (self.param, self.roi_box) =
                 self.tddfa
                     .run(frame, self.face_box, &self.pts_3d, CropPolicy::Box)?;
-            self.pts_3d = self.tddfa.recon_vers(self.param, self.face_box);
+            self.pts_3d = self.tddfa.recon_vers(&self.param, self.face_box);
 
             (self.param, self.roi_box) =
                 self.tddfa
                     .run(frame, self.face_box, &self.pts_3d, CropPolicy::Landmark)?;
-            self.pts_3d = self.tddfa.recon_vers(self.param, self.face_box);
+            self.pts_3d = self.tddfa.recon_vers(&self.param, self.face_box);
 
             self.first_iteration = false;
         } else {
@@ -80,26 +229,72 @@ impl ProcessHeadPose {
             if (self.roi_box[2] - self.roi_box[0]).abs() * (self.roi_box[3] - self.roi_box[1]).abs()
                 < 2020.
             {
+                landmarks_reliable = false;
                 (self.param, self.roi_box) =
                     self.tddfa
                         .run(frame, self.face_box, &self.pts_3d, CropPolicy::Box)?;
             }
 
-            // make sure the roi_box is not out of the frame
-            if self.roi_box[0] < 0. {
-                self.roi_box[0] = 0.;
+            // make sure the roi_box is not out of the frame, or of the region of interest
+            if self.roi_box[0] < search_area.x as f32 {
+                self.roi_box[0] = search_area.x as f32;
             }
-            if self.roi_box[1] < 0. {
-                self.roi_box[1] = 0.;
+            if self.roi_box[1] < search_area.y as f32 {
+                self.roi_box[1] = search_area.y as f32;
             }
-            if self.roi_box[2] > frame.size()?.width as f32 {
-                self.roi_box[2] = frame.size()?.width as f32;
+            if self.roi_box[2] > (search_area.x + search_area.width) as f32 {
+                self.roi_box[2] = (search_area.x + search_area.width) as f32;
             }
-            if self.roi_box[3] > frame.size()?.height as f32 {
-                self.roi_box[3] = frame.size()?.height as f32;
+            if self.roi_box[3] > (search_area.y + search_area.height) as f32 {
+                self.roi_box[3] = (search_area.y + search_area.height) as f32;
             }
-            self.pts_3d = self.tddfa.recon_vers(self.param, self.roi_box);
+            self.pts_3d = self.tddfa.recon_vers(&self.param, self.roi_box);
         }
+
+        // Frames where the landmarks don't fit a face (hands in front of it, motion blur) are dropped until the quality recovers
+        // * A poor fit is never sent, even after many dropped frames, the pose would jump to it and back
+        let mut landmark_face = face::Rect::around(&self.pts_3d[0], &self.pts_3d[1]);
+        self.quality = self.landmark_quality(&previous.1, &landmark_face);
+        let mut redetected = false;
+        if (self.tracking_state == TrackingState::Tracking || self.rejected_frames > 0)
+            && self.quality < self.min_quality
+        {
+            // The landmarks may have locked onto something else, the crop follows the detected face instead
+            self.detect_faces(frame, search_area)?;
+            if let Some(face) = self.pick_face(frame, input.captured_at)? {
+                redetected = true;
+                self.tracked_face = Some(face);
+                self.face_box = [
+                    face.x - 50.,
+                    face.y - 50.,
+                    face.right() + 50.,
+                    face.bottom() + 50.,
+                ];
+                (self.param, self.roi_box) =
+                    self.tddfa
+                        .run(frame, self.face_box, &self.pts_3d, CropPolicy::Box)?;
+                self.pts_3d = self.tddfa.recon_vers(&self.param, self.roi_box);
+                landmark_face = face::Rect::around(&self.pts_3d[0], &self.pts_3d[1]);
+                // * The jump is measured against the detector box, the previous roi box may be stale
+                let detector_box = [face.x, face.y, face.right(), face.bottom()];
+                self.quality = self.landmark_quality(&detector_box, &landmark_face);
+            }
+
+            if self.quality < self.min_quality {
+                self.rejected_frames = self.rejected_frames.saturating_add(1);
+                // * Without a detected face, the next crop starts from the previous fit
+                if !redetected {
+                    (self.param, self.roi_box, self.pts_3d) = previous;
+                }
+                // Past a few frames the face counts as lost, so the loss policy holds or recenters the pose
+                if self.rejected_frames >= QUALITY_MAX_REJECTED_FRAMES {
+                    self.lose_track();
+                }
+                return Ok(PoseSample::new(self.last_pose, input));
+            }
+        }
+        self.rejected_frames = 0;
+
         let (p, pose) = calc_pose(&self.param);
 
         let (point2d, distance) = gen_point2d(
@@ -112,33 +307,70 @@ impl ProcessHeadPose {
         );
 
         let (centroid, distance) =
-            self.get_coordintes_and_depth(pose, distance, point2d, &self.roi_box);
+            self.get_coordintes_and_depth(pose, distance, point2d, &self.roi_box, frame_size);
+        let pose_data = [
+            centroid[0],
+            -centroid[1],
+            distance,
+            pose[0],
+            -pose[1],
+            pose[2],
+        ];
+
+        self.frames_since_detection += 1;
+        if redetected || !self.needs_detection(landmarks_reliable, &landmark_face) {
+            self.tracked_face = Some(landmark_face);
+            self.tracked_at = Some(input.captured_at);
+            if self.manual_target.is_some() {
+                self.manual_target = Some(landmark_face.center());
+            }
+            self.face_box = [
+                landmark_face.x - 50.,
+                landmark_face.y - 50.,
+                landmark_face.right() + 50.,
+                landmark_face.bottom() + 50.,
+            ];
+            self.last_pose = pose_data;
+            return Ok(PoseSample::new(pose_data, input));
+        }
 
         // detect any faces, if there are no faces, return the previous values
         // let mut rng = rand::thread_rng();
         // if rng.gen::<f64>() > 0.9 {
-        let face_detected = self.face_detector.detect(frame.clone()).unwrap();
+        self.detect_faces(frame, search_area)?;
 
-        if face_detected[0] < 1. {
-            return Ok(return_data);
+        let face = match self.pick_face(frame, input.captured_at)? {
+            Some(face) => face,
+            None => {
+                self.lose_track();
+                return Ok(PoseSample::new(return_data, input));
+            }
+        };
+        self.tracking_state = TrackingState::Tracking;
+        self.tracked_face = Some(face);
+        self.tracked_at = Some(input.captured_at);
+        // * The picked point follows the face, so it stays selected as the player moves
+        if self.manual_target.is_some() {
+            self.manual_target = Some(face.center());
         }
         self.face_box = [
-            face_detected[0] - 50.,
-            face_detected[1] - 50.,
-            face_detected[0] + face_detected[2] + 50.,
-            face_detected[1] + face_detected[3] + 50.,
+            face.x - 50.,
+            face.y - 50.,
+            face.right() + 50.,
+            face.bottom() + 50.,
         ];
 
-        return_data = [
-            centroid[0],
-            -centroid[1],
-            distance,
-            pose[0],
-            -pose[1],
-            pose[2],
-        ];
+        // Remembering who is tracked, the signature slowly follows the lighting
+        if self.reidentify {
+            let latest = FaceSignature::new(frame, &face, self.tddfa.layout.shape(&self.param))?;
+            match &mut self.signature {
+                Some(signature) => signature.update(&latest)?,
+                None => self.signature = Some(latest),
+            }
+        }
 
-        Ok(return_data)
+        self.last_pose = pose_data;
+        Ok(PoseSample::new(pose_data, input))
     }
 }
 
@@ -146,29 +378,41 @@ impl ProcessHeadPose {
 #[ignore = "Can only test this offline since it requires webcam, run cargo test -- --ignored"]
 #[allow(unused_variables)]
 pub fn test_process_head_pose() -> Result<()> {
-    use crate::structs::camera::ThreadedCamera;
+    use crate::structs::{camera::ThreadedCamera, mailbox::FrameMailbox, source::CameraSource};
     // use crate::utils::image::crop_img;
     use crate::utils::visualize::draw_landmark;
     use opencv::highgui;
 
-    let (tx, rx) = crossbeam_channel::unbounded::<Mat>();
-    let mut thr_cam = ThreadedCamera::start_camera_thread(tx, 0, "Test Camera".to_owned())?;
-
-    let mut face_detector = FaceDetect::new().unwrap();
-    let mut head_pose = ProcessHeadPose::new(120)?;
+    let mailbox = FrameMailbox::new();
+    let mut frames = mailbox.subscribe_tracker();
+    let device = ThreadedCamera::get_available_cameras()?
+        .into_values()
+        .find(|device| device.index >= 0)
+        .context("No camera found")?;
+    let camera = CameraSource::open(
+        device.id,
+        device.name,
+        Default::default(),
+        None,
+        Default::default(),
+    )?;
+    let mut thr_cam =
+        ThreadedCamera::start_source_thread(mailbox, Box::new(camera), false, Default::default());
+
+    let mut face_detector = FaceDetect::new(crate::consts::BLAZE_FACE_MODEL).unwrap();
+    let mut head_pose = ProcessHeadPose::new(&ModelSet::builtin()?)?;
 
     let window = "video capture";
     highgui::named_window(window, highgui::WINDOW_AUTOSIZE)?;
 
-    let mut frame = rx.recv()?;
-
     loop {
-        frame = match rx.try_recv() {
-            Ok(result) => result,
-            Err(_) => frame.clone(),
+        let frame = match frames.recv_timeout(std::time::Duration::from_secs(5)) {
+            Some(frame) => frame,
+            None => break,
         };
 
         let data = head_pose.single_iter(&frame)?;
+        let frame = frame.mat;
 
         // frame = draw_landmark(
         //     frame,
@@ -238,3 +482,277 @@ pub fn test_process_head_pose() -> Result<()> {
     thr_cam.shutdown();
     Ok(())
 }
+
+#[test]
+pub fn test_process_head_pose_video_file() -> Result<()> {
+    use crate::source::{write_test_video, FrameSource};
+    use crate::structs::source::VideoFileSource;
+
+    let path = std::env::temp_dir().join("stableview_test_process_head_pose.avi");
+    let path = path.to_str().context("Invalid temporary path")?;
+    write_test_video(path, 20, 30.)?;
+
+    let mut source = VideoFileSource::open(path, 0., false)?;
+    let mut head_pose = ProcessHeadPose::new(&ModelSet::builtin()?)?;
+
+    let mut frame = Mat::default();
+    while source.read(&mut frame)? {
+        // * There is no face in the video, so the pose stays at its default. Like the tracking loop, frames that error out are skipped
+        if let Ok(sample) = head_pose.single_iter(&Frame::new(frame.clone())) {
+            assert_eq!(sample.data, [0.; 6]);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn test_pipeline_synthetic_source() -> Result<()> {
+    use crate::{
+        enums::synthetic_pattern::SyntheticPattern,
+        filter::EuroDataFilter,
+        structs::{
+            camera::ThreadedCamera, mailbox::FrameMailbox, network::SocketNetwork,
+            source::SyntheticSource,
+        },
+    };
+    use std::{net::UdpSocket, sync::atomic::Ordering};
+
+    // Stand-in for opentrack
+    let receiver = UdpSocket::bind("127.0.0.1:0")?;
+    receiver.set_read_timeout(Some(std::time::Duration::from_secs(5)))?;
+    let port = receiver.local_addr()?.port();
+
+    let mailbox = FrameMailbox::new();
+    let mut frames = mailbox.subscribe_tracker();
+    let source = SyntheticSource::new(SyntheticPattern::MovingRectangle, 640, 480, Some(20));
+    let mut thr_cam = ThreadedCamera::start_source_thread(
+        mailbox.clone(),
+        Box::new(source),
+        false,
+        Default::default(),
+    );
+
+    let mut head_pose = ProcessHeadPose::new(&ModelSet::builtin()?)?;
+    let mut euro_filter = EuroDataFilter::new(0.0025, 0.01);
+    let mut socket_network = SocketNetwork::new("127.0.0.1".to_owned(), port.to_string())?;
+
+    let mut buffer = [0u8; 128];
+    // * Inference is slower than the source, so most frames are replaced before being processed
+    while let Some(frame) = frames.recv_timeout(std::time::Duration::from_millis(500)) {
+        // * Like the tracking loop, frames that error out are skipped
+        let sample = match head_pose.single_iter(&frame) {
+            Ok(sample) => sample,
+            Err(_) => continue,
+        };
+        let sample = euro_filter.filter_data(sample, None, None);
+        let latency = socket_network.send(&sample)?;
+
+        // The pose keeps the capture time of its frame, so the latency covers the whole pipeline
+        assert_eq!(sample.seq, frame.seq);
+        assert_eq!(sample.captured_at, frame.captured_at);
+        assert!(latency <= frame.captured_at.elapsed());
+        mailbox.stats.record_latency(latency);
+
+        // opentrack receives the 6 values as f64
+        let received = receiver.recv(&mut buffer)?;
+        assert!(received >= 48);
+        for (i, value) in sample.data.iter().enumerate() {
+            let bytes: [u8; 8] = buffer[i * 8..(i + 1) * 8].try_into()?;
+            assert_eq!(f64::from_ne_bytes(bytes), *value as f64);
+        }
+    }
+
+    thr_cam.shutdown();
+
+    let stats = &mailbox.stats;
+    assert_eq!(stats.captured.load(Ordering::SeqCst), 20);
+    assert!(stats.processed.load(Ordering::SeqCst) >= 1);
+    assert_eq!(
+        stats.processed.load(Ordering::SeqCst) + stats.dropped.load(Ordering::SeqCst),
+        20
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn test_coordinates_follow_frame_orientation() -> Result<()> {
+    let head_pose = ProcessHeadPose::new(&ModelSet::builtin()?)?;
+
+    // A head in the center of the frame is at the same place in landscape and portrait frames
+    let landscape_box = [270., 190., 370., 290.];
+    let portrait_box = [190., 270., 290., 370.];
+    let (landscape, _) = head_pose.get_coordintes_and_depth(
+        [0.; 3],
+        60.,
+        vec![],
+        &landscape_box,
+        Size::new(640, 480),
+    );
+    let (portrait, _) = head_pose.get_coordintes_and_depth(
+        [0.; 3],
+        60.,
+        vec![],
+        &portrait_box,
+        Size::new(480, 640),
+    );
+    assert_eq!(landscape, portrait);
+    assert_eq!(landscape, [-8., 9.]);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_reidentification_timeout() -> Result<()> {
+    use crate::{
+        enums::synthetic_pattern::SyntheticPattern, face::Face, source::FrameSource,
+        structs::source::SyntheticSource,
+    };
+
+    // The person was tracked under a warm light, which turned blue while they were away
+    let mut warm_frame = Mat::default();
+    SyntheticSource::new(
+        SyntheticPattern::SolidColor(120., 160., 220.),
+        320,
+        240,
+        None,
+    )
+    .read(&mut warm_frame)?;
+    let mut frame = Mat::default();
+    SyntheticSource::new(SyntheticPattern::SolidColor(255., 0., 0.), 320, 240, None)
+        .read(&mut frame)?;
+
+    let mut head_pose = ProcessHeadPose::new(&ModelSet::builtin()?)?;
+    let rect = face::Rect::at(100., 60.).ending_at(200., 180.);
+    let shape = vec![1.; head_pose.tddfa.layout.shape_dim];
+    head_pose.signature = Some(FaceSignature::new(&warm_frame, &rect, &shape)?);
+    head_pose.tracking_state = TrackingState::Lost;
+    let tracked_at = Instant::now();
+    head_pose.tracked_at = Some(tracked_at);
+    head_pose.detected_faces = vec![Face {
+        rect,
+        confidence: 1.,
+        landmarks: None,
+    }];
+
+    // The face doesn't match the signature, it is only taken once the timeout is over
+    assert!(head_pose
+        .pick_face(&frame, tracked_at + REID_TIMEOUT / 2)?
+        .is_none());
+    assert!(head_pose.signature.is_some());
+
+    let picked = head_pose.pick_face(&frame, tracked_at + REID_TIMEOUT)?;
+    assert_eq!(picked.map(|face| face.center()), Some(rect.center()));
+    assert!(head_pose.signature.is_none());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_detection_schedule() -> Result<()> {
+    let mut head_pose = ProcessHeadPose::new(&ModelSet::builtin()?)?;
+    let tracked_face = face::Rect::at(100., 100.).ending_at(200., 200.);
+    let landmark_face = face::Rect::at(110., 110.).ending_at(190., 190.);
+    head_pose.detection_interval = 3;
+
+    // Nothing is tracked yet
+    assert!(head_pose.needs_detection(true, &landmark_face));
+
+    // The landmarks follow the face until the interval is over
+    head_pose.tracking_state = TrackingState::Tracking;
+    head_pose.tracked_face = Some(tracked_face);
+    for frames_since_detection in 1..3 {
+        head_pose.frames_since_detection = frames_since_detection;
+        assert!(!head_pose.needs_detection(true, &landmark_face));
+    }
+    head_pose.frames_since_detection = 3;
+    assert!(head_pose.needs_detection(true, &landmark_face));
+
+    // Forced when the landmarks can't be trusted, moved away from the face, or the face was lost
+    head_pose.frames_since_detection = 1;
+    assert!(head_pose.needs_detection(false, &landmark_face));
+    assert!(head_pose.needs_detection(true, &landmark_face.translate(150., 0.)));
+    head_pose.tracking_state = TrackingState::Lost;
+    assert!(head_pose.needs_detection(true, &landmark_face));
+
+    // Every frame is checked with the default interval
+    head_pose.tracking_state = TrackingState::Tracking;
+    head_pose.detection_interval = 1;
+    assert!(head_pose.needs_detection(true, &landmark_face));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_quality_gate() -> Result<()> {
+    use crate::{
+        enums::synthetic_pattern::SyntheticPattern,
+        source::FrameSource,
+        structs::{source::SyntheticSource, state::AppConfig},
+    };
+
+    // * A still frame without a face, so every fit is the same and the detector finds nothing
+    let mut mat = Mat::default();
+    SyntheticSource::new(SyntheticPattern::SolidColor(255., 0., 0.), 640, 480, None)
+        .read(&mut mat)?;
+    let mut head_pose = ProcessHeadPose::new(&ModelSet::builtin()?)?;
+    head_pose.single_iter(&Frame::new(mat.clone()))?;
+
+    // Fits below the minimum quality are dropped while tracking, the last pose is sent again
+    head_pose.tracking_state = TrackingState::Tracking;
+    head_pose.min_quality = 1.1;
+    head_pose.last_pose = [1.; 6];
+    let (param, roi_box) = (head_pose.param.clone(), head_pose.roi_box);
+    for rejected_frames in 1..=QUALITY_MAX_REJECTED_FRAMES {
+        let sample = head_pose.single_iter(&Frame::new(mat.clone()))?;
+        assert_eq!(sample.data, [1.; 6]);
+        assert_eq!(head_pose.rejected_frames, rejected_frames);
+        // * No face was detected to crop from, the previous fit is kept
+        assert_eq!((&head_pose.param, head_pose.roi_box), (&param, roi_box));
+    }
+
+    // After a few in a row the face counts as lost, the loss policy takes over
+    assert_eq!(head_pose.tracking_state, TrackingState::Lost);
+
+    // Nothing is dropped with the default minimum quality
+    head_pose.tracking_state = TrackingState::Tracking;
+    head_pose.min_quality = AppConfig::default().min_quality;
+    head_pose.single_iter(&Frame::new(mat.clone()))?;
+    assert_eq!(head_pose.rejected_frames, 0);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_sustained_low_quality() -> Result<()> {
+    use crate::{
+        enums::synthetic_pattern::SyntheticPattern, source::FrameSource,
+        structs::source::SyntheticSource,
+    };
+
+    let mut mat = Mat::default();
+    SyntheticSource::new(SyntheticPattern::SolidColor(255., 0., 0.), 640, 480, None)
+        .read(&mut mat)?;
+    let mut head_pose = ProcessHeadPose::new(&ModelSet::builtin()?)?;
+    head_pose.single_iter(&Frame::new(mat.clone()))?;
+
+    head_pose.tracking_state = TrackingState::Tracking;
+    head_pose.min_quality = 1.1;
+    head_pose.last_pose = [1.; 6];
+
+    // No poor fit gets through while the quality stays low, the pose never jumps away from the held one
+    for frame in 1..=QUALITY_MAX_REJECTED_FRAMES * 3 {
+        let sample = head_pose.single_iter(&Frame::new(mat.clone()))?;
+        assert_eq!(sample.data, [1.; 6]);
+        assert_eq!(head_pose.rejected_frames, frame);
+    }
+    assert_eq!(head_pose.tracking_state, TrackingState::Lost);
+
+    // Fits are taken again once the quality recovers
+    head_pose.min_quality = 0.;
+    head_pose.single_iter(&Frame::new(mat.clone()))?;
+    assert_eq!(head_pose.rejected_frames, 0);
+
+    Ok(())
+}
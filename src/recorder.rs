@@ -0,0 +1,268 @@
+/// Recording of the captured frames and of the estimated poses, attached to bug reports and replayed through the pipeline
+use crate::{
+    consts::{APP_NAME, RECORDING_QUALITY},
+    structs::{
+        camera::FrameTransform,
+        frame::{Frame, PoseSample},
+        recorder::{SessionHeader, SessionRecord, SessionRecorder},
+    },
+    utils::image::to_bgr,
+};
+
+use anyhow::{Context, Result};
+use opencv::{
+    prelude::{MatTraitConst, VideoWriterTrait, VideoWriterTraitConst},
+    videoio::{self, VideoWriter},
+};
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+impl SessionRecorder {
+    // Directory next to the logs the recordings are saved in
+    pub fn recordings_dir() -> Result<PathBuf> {
+        let dirs = directories::ProjectDirs::from("rs", "", APP_NAME)
+            .context("Unable to find the data directory")?;
+        Ok(dirs.data_dir().join("recordings"))
+    }
+
+    // Sidecar of the recorded video
+    pub fn sidecar_path(video_path: &Path) -> PathBuf {
+        video_path.with_extension("jsonl")
+    }
+
+    // Starts a new recording named after the current time, fps is the rate the video is played back at
+    // frame_transform is the rotation/mirroring already applied to the frames
+    pub fn start(directory: &Path, fps: f64, frame_transform: FrameTransform) -> Result<Self> {
+        fs::create_dir_all(directory)
+            .with_context(|| format!("Unable to create {}", directory.display()))?;
+
+        // * A counter keeps the sessions started within the same millisecond from overwriting each other
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let mut name = format!("session-{millis}");
+        let mut count = 1;
+        let (video_path, sidecar_path, sidecar) = loop {
+            let video_path = directory.join(format!("{name}.avi"));
+            let sidecar_path = Self::sidecar_path(&video_path);
+            match File::options()
+                .write(true)
+                .create_new(true)
+                .open(&sidecar_path)
+            {
+                Ok(file) if !video_path.exists() => break (video_path, sidecar_path, file),
+                Ok(file) => {
+                    drop(file);
+                    fs::remove_file(&sidecar_path)?;
+                }
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => {}
+                Err(error) => {
+                    return Err(error)
+                        .with_context(|| format!("Unable to create {}", sidecar_path.display()))
+                }
+            }
+            name = format!("session-{millis}-{count}");
+            count += 1;
+        };
+
+        let mut sidecar = BufWriter::new(sidecar);
+        serde_json::to_writer(&mut sidecar, &SessionHeader { frame_transform })?;
+        writeln!(sidecar)?;
+
+        tracing::info!("Recording session to {}", video_path.display());
+
+        Ok(Self {
+            video_path,
+            sidecar_path,
+            fps,
+            writer: None,
+            sidecar,
+            started_at: Instant::now(),
+            frames_written: 0,
+        })
+    }
+
+    // Appends the frame to the video and its face box, roi box and poses to the sidecar
+    pub fn record(
+        &mut self,
+        frame: &Frame,
+        face_box: [f32; 4],
+        roi_box: [f32; 4],
+        raw: &PoseSample,
+        filtered: Option<&PoseSample>,
+    ) -> Result<()> {
+        let frame_size = frame.mat.size()?;
+        if self.writer.is_none() {
+            let video_path = self.video_path.to_str().context("Invalid recording path")?;
+            // * MJPEG is built into OpenCV, so recordings work without FFmpeg
+            let mut writer = VideoWriter::new(
+                video_path,
+                VideoWriter::fourcc('M', 'J', 'P', 'G')?,
+                self.fps,
+                frame_size,
+                true,
+            )?;
+            if !writer.is_opened()? {
+                return Err(anyhow::anyhow!("Unable to create {video_path}"));
+            }
+            writer.set(videoio::VIDEOWRITER_PROP_QUALITY, RECORDING_QUALITY)?;
+            self.writer = Some((writer, frame_size));
+        }
+
+        let (writer, recorded_size) = self.writer.as_mut().unwrap();
+        if frame_size != *recorded_size {
+            return Err(anyhow::anyhow!(
+                "Frame size changed from {:?} to {:?} during the recording",
+                recorded_size,
+                frame_size
+            ));
+        }
+        // Mono (IR) and BGRA frames
+        writer.write(&to_bgr(&frame.mat)?)?;
+
+        let record = SessionRecord {
+            frame: self.frames_written,
+            seq: frame.seq,
+            time_ms: frame
+                .captured_at
+                .saturating_duration_since(self.started_at)
+                .as_secs_f64()
+                * 1000.,
+            face_box,
+            roi_box,
+            raw: raw.data,
+            filtered: filtered.map(|filtered| filtered.data),
+        };
+        serde_json::to_writer(&mut self.sidecar, &record)?;
+        writeln!(self.sidecar)?;
+        self.frames_written += 1;
+
+        Ok(())
+    }
+
+    // Closes the video and flushes the sidecar, dropping the recorder does the same without reporting errors
+    pub fn finish(mut self) -> Result<PathBuf> {
+        if let Some((mut writer, _)) = self.writer.take() {
+            writer.release()?;
+        }
+        self.sidecar.flush()?;
+
+        tracing::info!(
+            "Recorded {} frames to {}",
+            self.frames_written,
+            self.video_path.display()
+        );
+        Ok(self.video_path)
+    }
+
+    // Reads back the header and the poses of a recording, to compare them with a replay of the video
+    pub fn read_sidecar(path: &Path) -> Result<(SessionHeader, Vec<SessionRecord>)> {
+        let file =
+            File::open(path).with_context(|| format!("Unable to open {}", path.display()))?;
+        let mut lines = BufReader::new(file).lines();
+        let header = serde_json::from_str(&lines.next().context("Empty sidecar")??)?;
+        let records = lines
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect::<Result<_>>()?;
+        Ok((header, records))
+    }
+
+    // Header of the recording the video belongs to, None for a video that wasn't recorded by the tracker
+    pub fn read_header(video_path: &Path) -> Option<SessionHeader> {
+        let file = File::open(Self::sidecar_path(video_path)).ok()?;
+        let line = BufReader::new(file).lines().next()?.ok()?;
+        serde_json::from_str(&line).ok()
+    }
+}
+
+#[test]
+pub fn test_session_recorder() -> Result<()> {
+    use crate::{
+        enums::{rotation::Rotation, synthetic_pattern::SyntheticPattern},
+        source::{open_frame_source, FrameSource},
+        structs::{
+            camera::CameraDevice,
+            source::{SyntheticSource, VideoFileSource},
+        },
+    };
+    use opencv::prelude::Mat;
+
+    let directory = std::env::temp_dir().join("stableview_test_session_recorder");
+    let frame_transform = FrameTransform {
+        rotation: Rotation::Rotate90,
+        ..Default::default()
+    };
+    let mut recorder = SessionRecorder::start(&directory, 30., frame_transform)?;
+    let sidecar_path = recorder.sidecar_path.clone();
+
+    // Sessions started right after each other are kept apart
+    let other = SessionRecorder::start(&directory, 30., frame_transform)?;
+    assert_ne!(other.sidecar_path, sidecar_path);
+    assert_ne!(other.video_path, recorder.video_path);
+
+    let mut source = SyntheticSource::new(SyntheticPattern::MovingRectangle, 320, 240, Some(5));
+    let mut mat = Mat::default();
+    let mut seq = 0;
+    while source.read(&mut mat)? {
+        seq += 2;
+        let mut frame = Frame::new(mat.clone());
+        frame.seq = seq;
+        let raw = PoseSample::new([seq as f32; 6], &frame);
+        let filtered = PoseSample::new([seq as f32 / 2.; 6], &frame);
+        // Nothing sent to OpenTrack for the second frame
+        let filtered = (seq != 4).then_some(&filtered);
+        recorder.record(&frame, [1., 2., 3., 4.], [5., 6., 7., 8.], &raw, filtered)?;
+    }
+
+    // A frame of another size can't be added to the video
+    let frame = Frame::new(Mat::new_rows_cols_with_default(
+        120,
+        160,
+        opencv::core::CV_8UC3,
+        opencv::core::Scalar::all(0.),
+    )?);
+    let sample = PoseSample::new([0.; 6], &frame);
+    assert!(recorder
+        .record(&frame, [0.; 4], [0.; 4], &sample, Some(&sample))
+        .is_err());
+
+    let video_path = recorder.finish()?;
+
+    // Every recorded frame can be replayed and matched with its sidecar line
+    let (header, records) = SessionRecorder::read_sidecar(&sidecar_path)?;
+    assert_eq!(header.frame_transform, frame_transform);
+    assert_eq!(records.len(), 5);
+    assert_eq!(records[4].frame, 4);
+    assert_eq!(records[4].seq, 10);
+    assert_eq!(records[4].raw, [10.; 6]);
+    assert_eq!(records[4].filtered, Some([5.; 6]));
+    assert_eq!(records[1].filtered, None);
+    assert_eq!(records[0].roi_box, [5., 6., 7., 8.]);
+
+    let mut replay = VideoFileSource::open(video_path.to_str().unwrap(), 0., false)?;
+    let mut frame_count = 0;
+    while replay.read(&mut mat)? {
+        assert_eq!(mat.size()?, opencv::core::Size::new(320, 240));
+        frame_count += 1;
+    }
+    assert_eq!(frame_count, records.len());
+
+    // The frames were already rotated, replaying with the same settings doesn't rotate them again
+    let mut replay = open_frame_source(
+        video_path.to_str().unwrap(),
+        0.,
+        CameraDevice::none(),
+        Default::default(),
+        None,
+        Default::default(),
+        frame_transform,
+    )?;
+    assert!(replay.read(&mut mat)?);
+    assert_eq!(mat.size()?, opencv::core::Size::new(320, 240));
+    drop(replay);
+
+    fs::remove_dir_all(&directory)?;
+    Ok(())
+}
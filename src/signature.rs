@@ -0,0 +1,200 @@
+/// Re-identification of the tracked person, from the colors of their face and the shape of their 3D face model
+use crate::{
+    consts::{REID_MIN_APPEARANCE, REID_MIN_SHAPE, REID_UPDATE_RATE},
+    enums::crop_policy::CropPolicy,
+    face::{Face, Rect},
+    structs::{signature::FaceSignature, tddfa::Tddfa},
+    utils::image::{crop_img, to_bgr},
+};
+
+use anyhow::Result;
+use opencv::{
+    core::{self, Vector},
+    imgproc,
+    prelude::{Mat, MatTraitConst},
+};
+
+// Hue/saturation histogram, less sensitive to the lighting than the brightness
+fn face_histogram(frame: &Mat, face: &Rect) -> Result<Mat> {
+    let crop = crop_img(frame, &[face.x, face.y, face.right(), face.bottom()])?;
+    if crop.empty() {
+        return Err(anyhow::anyhow!("Face {} is outside of the frame", face));
+    }
+
+    let mut hsv = Mat::default();
+    imgproc::cvt_color(&to_bgr(&crop)?, &mut hsv, imgproc::COLOR_BGR2HSV, 0)?;
+
+    let mut histogram = Mat::default();
+    imgproc::calc_hist(
+        &Vector::<Mat>::from_iter([hsv]),
+        &Vector::from_slice(&[0, 1]),
+        &core::no_array(),
+        &mut histogram,
+        &Vector::from_slice(&[16, 16]),
+        &Vector::from_slice(&[0., 180., 0., 256.]),
+        false,
+    )?;
+
+    let mut normalized = Mat::default();
+    core::normalize(
+        &histogram,
+        &mut normalized,
+        1.,
+        0.,
+        core::NORM_L1,
+        -1,
+        &core::no_array(),
+    )?;
+    Ok(normalized)
+}
+
+impl FaceSignature {
+    // Signature of the face tracked in this frame, shape being the shape coefficients of the landmark model
+    pub fn new(frame: &Mat, face: &Rect, shape: &[f32]) -> Result<Self> {
+        Ok(Self {
+            histogram: face_histogram(frame, face)?,
+            shape: shape.to_vec(),
+        })
+    }
+
+    // Blends in the latest signature, to follow slow lighting changes
+    pub fn update(&mut self, latest: &FaceSignature) -> Result<()> {
+        let mut histogram = Mat::default();
+        core::add_weighted(
+            &self.histogram,
+            1. - REID_UPDATE_RATE as f64,
+            &latest.histogram,
+            REID_UPDATE_RATE as f64,
+            0.,
+            &mut histogram,
+            -1,
+        )?;
+        self.histogram = histogram;
+
+        for (coefficient, latest) in self.shape.iter_mut().zip(&latest.shape) {
+            *coefficient += (latest - *coefficient) * REID_UPDATE_RATE;
+        }
+        Ok(())
+    }
+
+    // 1 for the same colors, 0 for colors that have nothing in common
+    pub fn appearance_similarity(&self, histogram: &Mat) -> Result<f32> {
+        let distance =
+            imgproc::compare_hist(&self.histogram, histogram, imgproc::HISTCMP_BHATTACHARYYA)?;
+        Ok(1. - distance as f32)
+    }
+
+    // Cosine similarity of the shape coefficients, 1 for the same face shape
+    pub fn shape_similarity(&self, shape: &[f32]) -> f32 {
+        // * Shapes of another model don't compare
+        if self.shape.len() != shape.len() {
+            return 0.;
+        }
+
+        let dot = self
+            .shape
+            .iter()
+            .zip(shape)
+            .map(|(a, b)| a * b)
+            .sum::<f32>();
+        let norm = |shape: &[f32]| shape.iter().map(|c| c * c).sum::<f32>().sqrt();
+        let norms = norm(&self.shape) * norm(shape);
+        if norms > 0. {
+            dot / norms
+        } else {
+            0.
+        }
+    }
+
+    // Finds the face of the tracked person among the detected ones, None if they aren't back yet
+    // * The landmark model is only run on faces with a matching appearance, and only while the person is lost
+    pub fn find_match(
+        &self,
+        frame: &Mat,
+        faces: &[Face],
+        tddfa: &mut Tddfa,
+        pts_3d: &[Vec<f32>],
+    ) -> Result<Option<Rect>> {
+        let mut best_match = None;
+        let mut best_score = 0.;
+
+        for face in faces {
+            let appearance = self.appearance_similarity(&face_histogram(frame, &face.rect)?)?;
+            if appearance < REID_MIN_APPEARANCE {
+                continue;
+            }
+
+            let face_box = [
+                face.rect.x,
+                face.rect.y,
+                face.rect.right(),
+                face.rect.bottom(),
+            ];
+            let (param, _) = tddfa.run(frame, face_box, pts_3d, CropPolicy::Box)?;
+            let shape = self.shape_similarity(tddfa.layout.shape(&param));
+            if shape < REID_MIN_SHAPE {
+                continue;
+            }
+
+            if appearance + shape > best_score {
+                best_score = appearance + shape;
+                best_match = Some(face.rect);
+            }
+        }
+
+        Ok(best_match)
+    }
+}
+
+#[test]
+pub fn test_face_signature() -> Result<()> {
+    use crate::{
+        enums::synthetic_pattern::SyntheticPattern, source::FrameSource,
+        structs::source::SyntheticSource,
+    };
+
+    // Left half of the frame is skin colored, right half is blue
+    let mut frame = Mat::default();
+    SyntheticSource::new(SyntheticPattern::SolidColor(255., 0., 0.), 160, 120, None)
+        .read(&mut frame)?;
+    imgproc::rectangle(
+        &mut frame,
+        core::Rect::new(0, 0, 80, 120),
+        core::Scalar::new(120., 160., 220., 0.),
+        imgproc::FILLED,
+        imgproc::LINE_8,
+        0,
+    )?;
+
+    let left = Rect::at(10., 10.).ending_at(70., 110.);
+    let right = Rect::at(90., 10.).ending_at(150., 110.);
+    let shape: Vec<f32> = (0..40).map(|i| i as f32).collect();
+
+    let mut signature = FaceSignature::new(&frame, &left, &shape)?;
+    assert!(signature.appearance_similarity(&face_histogram(&frame, &left)?)? > 0.99);
+    assert!(
+        signature.appearance_similarity(&face_histogram(&frame, &right)?)? < REID_MIN_APPEARANCE
+    );
+
+    // The shape coefficients don't depend on their scale, only on their proportions
+    let same_shape: Vec<f32> = signature.shape.iter().map(|c| c * 2.).collect();
+    let other_shape: Vec<f32> = signature.shape.iter().map(|c| 39. - c).collect();
+    assert!(signature.shape_similarity(&same_shape) > 0.99);
+    assert!(signature.shape_similarity(&other_shape) < signature.shape_similarity(&same_shape));
+
+    // Blending in the same face keeps the signature matching it
+    let latest = FaceSignature::new(&frame, &left, &shape)?;
+    signature.update(&latest)?;
+    assert!(signature.appearance_similarity(&latest.histogram)? > 0.99);
+    assert!(signature.shape_similarity(&latest.shape) > 0.99);
+
+    // Shapes of a model with another number of coefficients never match
+    assert_eq!(signature.shape_similarity(&shape[..10]), 0.);
+
+    // Faces outside of the frame have no signature
+    assert!(
+        FaceSignature::new(&frame, &Rect::at(200., 200.).ending_at(250., 250.), &shape).is_err()
+    );
+
+    Ok(())
+}
@@ -9,11 +9,22 @@ use std::{
     thread,
 };
 
-use crossbeam_channel::{unbounded, Receiver, Sender};
 use opencv::{core::MatTraitConst, imgcodecs, prelude::Mat};
 
-use super::{camera::ThreadedCamera, release::Release, state::AppConfig};
+use super::{
+    camera::{
+        CameraControls, CameraDevice, CaptureFormat, CaptureRoi, FrameTransform, ThreadedCamera,
+    },
+    mailbox::{FrameMailbox, FrameSubscription},
+    model::ModelSet,
+    release::Release,
+    state::AppConfig,
+};
 use crate::consts::{APP_GITHUB_API, APP_VERSION, NO_VIDEO_IMG};
+use crate::enums::{
+    capture_backend::CaptureBackend, face_policy::FacePolicy, loss_policy::LossPolicy,
+    tracking_state::TrackingState,
+};
 use version_compare::{compare_to, Cmp};
 
 // * Adding this to another struct file
@@ -49,22 +60,51 @@ pub struct Config {
 
     pub selected_camera: String,
     pub hide_camera: bool,
+
+    pub input_path: String, // Video file played instead of the camera when not empty
+    pub playback_speed: f32,
+    pub capture_formats: HashMap<String, CaptureFormat>,
+    pub camera_controls: HashMap<String, CameraControls>,
+    pub capture_backend: CaptureBackend,
+    pub frame_transform: FrameTransform, // Rotation and mirroring applied to the frames before tracking
+    pub capture_roi: Arc<Mutex<Option<CaptureRoi>>>, // Read by the tracking thread, so it can be moved while tracking
+    pub preview_server: bool, // Stream the annotated preview over HTTP while tracking
+    pub preview_port: String,
+    pub preview_lan: bool, // Let other machines watch the preview, only this one can otherwise
+    pub face_policy: FacePolicy, // Which face is tracked when there are several
+    pub reidentify: bool,  // Only reacquire the same person after losing them
+    pub detection_threshold: Arc<AtomicF32>, // Minimum confidence of the detected faces
+    pub nms_threshold: Arc<AtomicF32>, // Overlap above which two detections are the same face
+    pub detection_interval: Arc<AtomicU32>, // Frames between two runs of the face detector
+    pub min_quality: Arc<AtomicF32>, // Frames with a lower landmark quality are dropped
+    pub loss_policy: LossPolicy, // What is sent to opentrack while the face is lost
+    pub loss_timeout: Arc<AtomicF32>, // Seconds the last pose is held before going back to the center
+    pub model: String,                // Folder of the models directory, or the built-in models
+    pub auto_resume: bool, // Keep reopening the camera when it disconnects instead of stopping tracking
 }
 
 // Contains configuration and state of the application and other data
 pub struct HeadTracker {
     pub config: Config,
 
-    pub camera_list: HashMap<String, i32>,
+    pub camera_list: HashMap<String, CameraDevice>, // Keyed by camera id
+    pub camera_formats: Vec<CaptureFormat>,         // Supported by the selected camera
+    pub model_list: Vec<String>,                    // Built-in and found in the models directory
 
     pub headtracker_thread: Option<thread::JoinHandle<()>>,
     pub headtracker_running: sync::Arc<AtomicBool>,
 
     pub should_exit: bool,
     pub error_tracker: Arc<Mutex<String>>,
-
-    pub sender: Sender<Mat>,
-    pub receiver: Receiver<Mat>,
+    pub show_advanced: bool,
+
+    pub frames: FrameMailbox,       // Latest frame from the camera thread
+    pub preview: FrameSubscription, // Frames shown in the GUI
+    pub pending_controls: Arc<Mutex<Option<CameraControls>>>, // Picked up by the camera thread to apply changes live
+    pub recording: Arc<AtomicBool>, // Whether the tracking thread records the session
+    pub detected_faces: Arc<Mutex<Vec<[f32; 4]>>>, // In the last frame, as fractions of the frame size
+    pub picked_face: Arc<Mutex<Option<(f32, f32)>>>, // Picked in the preview, handed over to the tracking thread
+    pub tracking_state: Arc<Mutex<TrackingState>>,   // Set by the tracking thread, shown in the GUI
     pub frame: Mat,
 
     pub release_info: Option<Release>,
@@ -85,14 +125,36 @@ impl Default for Config {
 
             selected_camera: AppConfig::default().selected_camera, // ? Maybe checking for new cameras in main.rs
             hide_camera: AppConfig::default().hide_camera,
+
+            input_path: AppConfig::default().input_path,
+            playback_speed: AppConfig::default().playback_speed,
+            capture_formats: AppConfig::default().capture_formats,
+            camera_controls: AppConfig::default().camera_controls,
+            capture_backend: AppConfig::default().capture_backend,
+            frame_transform: AppConfig::default().frame_transform,
+            capture_roi: Arc::new(Mutex::new(AppConfig::default().capture_roi)),
+            preview_server: AppConfig::default().preview_server,
+            preview_port: AppConfig::default().preview_port,
+            preview_lan: AppConfig::default().preview_lan,
+            face_policy: AppConfig::default().face_policy,
+            reidentify: AppConfig::default().reidentify,
+            detection_threshold: Arc::new(AtomicF32::new(AppConfig::default().detection_threshold)),
+            nms_threshold: Arc::new(AtomicF32::new(AppConfig::default().nms_threshold)),
+            detection_interval: Arc::new(AtomicU32::new(AppConfig::default().detection_interval)),
+            min_quality: Arc::new(AtomicF32::new(AppConfig::default().min_quality)),
+            loss_policy: AppConfig::default().loss_policy,
+            loss_timeout: Arc::new(AtomicF32::new(AppConfig::default().loss_timeout)),
+            model: AppConfig::default().model,
+            auto_resume: AppConfig::default().auto_resume,
         }
     }
 }
 
 impl Default for HeadTracker {
     fn default() -> Self {
-        // Setup channels for camera thread to headtracker thread
-        let (sender, receiver) = unbounded::<Mat>(); // ! bounded causes unwanted crashes bounded::<Mat>(1);
+        // Camera thread posts its frames here, the headtracker thread and the GUI each pick the latest one
+        let frames = FrameMailbox::new();
+        let preview = frames.subscribe_preview();
 
         let frame = match Mat::from_slice(NO_VIDEO_IMG) {
             Ok(frame) => frame.try_clone().unwrap(),
@@ -147,18 +209,26 @@ impl Default for HeadTracker {
                     HashMap::new()
                 }
             },
+            camera_formats: vec![],
+            model_list: ModelSet::available(),
 
             headtracker_thread: None,
             headtracker_running: Arc::new(AtomicBool::new(false)),
 
             should_exit: false,
             error_tracker: Arc::new(Mutex::new(String::new())),
+            show_advanced: false,
 
             version: APP_VERSION.to_string(),
             release_info: response_json,
 
-            sender,
-            receiver,
+            frames,
+            preview,
+            pending_controls: Arc::new(Mutex::new(None)),
+            recording: Arc::new(AtomicBool::new(false)),
+            detected_faces: Arc::new(Mutex::new(vec![])),
+            picked_face: Arc::new(Mutex::new(None)),
+            tracking_state: Arc::new(Mutex::new(TrackingState::default())),
             frame,
         }
     }
@@ -166,8 +236,8 @@ impl Default for HeadTracker {
 
 impl std::fmt::Display for Config {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "(min_cutoff : {}, beta: {}, ip: {}, port: {}, fps: {}, selected_camera: {}, hide_camera: {})", 
-        self.min_cutoff.load(Ordering::SeqCst), self.beta.load(Ordering::SeqCst), self.ip,self.port, self.fps.load(Ordering::SeqCst), self.selected_camera.clone(), self.hide_camera)
+        write!(f, "(min_cutoff : {}, beta: {}, ip: {}, port: {}, fps: {}, selected_camera: {}, hide_camera: {}, input_path: {}, playback_speed: {})", 
+        self.min_cutoff.load(Ordering::SeqCst), self.beta.load(Ordering::SeqCst), self.ip,self.port, self.fps.load(Ordering::SeqCst), self.selected_camera.clone(), self.hide_camera, self.input_path, self.playback_speed)
     }
 }
 
@@ -3,7 +3,71 @@ use std::{
     thread,
 };
 
+use crossbeam_channel::Receiver;
+use serde::{Deserialize, Serialize};
+
+use crate::enums::{camera_status::CameraStatus, rotation::Rotation};
+
 pub struct ThreadedCamera {
     pub cam_thread: Option<thread::JoinHandle<()>>, // Storing the thread
     pub keep_running: sync::Arc<AtomicBool>,        // Signal to stop the thread
+    pub status: Receiver<CameraStatus>, // Disconnections and reconnections of the source
+}
+
+// A camera found on the system, the id stays the same when the camera is replugged or the system reboots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CameraDevice {
+    pub id: String, // Device path or bus info reported by the OS, the name if there is none
+    pub name: String, // Human readable name
+    pub index: i32, // Index at the time of the query, -1 if there is no camera
+}
+
+// Resolution, frame rate and pixel format requested from the camera when it opens
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CaptureFormat {
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub fourcc: String, // ie. MJPG, YUYV
+}
+
+impl std::fmt::Display for CaptureFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{} @ {} fps {}",
+            self.width, self.height, self.fps, self.fourcc
+        )
+    }
+}
+
+// Image controls applied to the camera when it opens, None leaves the value chosen by the driver
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CameraControls {
+    pub auto_exposure: Option<bool>,
+    pub exposure: Option<f64>, // Units depend on the backend, 100 µs steps on V4L2 and log2 seconds on Windows
+    pub gain: Option<f64>,
+    pub brightness: Option<f64>,
+    pub contrast: Option<f64>,
+    pub focus: Option<f64>,         // Turns autofocus off when set
+    pub white_balance: Option<f64>, // Kelvin, turns auto white balance off when set
+}
+
+// Orientation fix applied to every frame before the head pose is estimated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FrameTransform {
+    pub rotation: Rotation,
+    pub mirror: bool, // Horizontal flip
+    pub flip: bool,   // Vertical flip
+}
+
+// Part of the frame the face is searched in, as fractions of the frame size so it survives resolution changes
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct CaptureRoi {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
 }
@@ -1,5 +1,21 @@
 use onnxruntime::session::Session;
+use opencv::core::Size;
+
+use crate::face::{Nms, PriorBoxes};
 
 pub struct FaceDetect {
     pub face_detector: Session<'static>,
+    pub priors: PriorBoxes, // Only depend on the input size, so they are computed once
+    pub score_threshold: f32, // Detections less confident than this are dropped
+    pub nms: Nms,
+}
+
+// Where the frame was placed in the square detector input, to map the detections back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Letterbox {
+    pub frame_size: Size,
+    pub resized: Size, // Frame size once scaled to fit the input
+    pub left: i32,     // Padding on the left of the frame
+    pub top: i32,      // Padding above the frame
+    pub input_size: i32,
 }
@@ -0,0 +1,19 @@
+use std::time::Instant;
+
+use opencv::prelude::Mat;
+
+// Frame along with when it was captured, carried through the whole pipeline to measure latency
+#[derive(Clone)]
+pub struct Frame {
+    pub mat: Mat,
+    pub seq: u64,             // Set by the mailbox, increasing with every captured frame
+    pub captured_at: Instant, // Monotonic, right after the frame was read from the source
+}
+
+// Head pose estimated from a frame, keeping the capture time of that frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoseSample {
+    pub data: [f32; 6], // x, y, z, yaw, pitch, roll
+    pub seq: u64,
+    pub captured_at: Instant,
+}
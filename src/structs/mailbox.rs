@@ -0,0 +1,33 @@
+use std::sync::{atomic::AtomicU64, Arc, Condvar, Mutex};
+
+use super::frame::Frame;
+
+// Single slot holding the latest captured frame, a new frame replaces the previous one instead of queuing behind it
+#[derive(Clone)]
+pub struct FrameMailbox {
+    pub slot: Arc<(Mutex<MailboxSlot>, Condvar)>, // Condvar wakes up the consumers when a frame is posted
+    pub stats: Arc<FrameStats>,
+}
+
+pub struct MailboxSlot {
+    pub frame: Option<Frame>,
+    pub seq: u64, // Number of the latest frame, 0 if no frame was posted yet
+}
+
+// Counters shown in the diagnostics
+#[derive(Default)]
+pub struct FrameStats {
+    pub captured: AtomicU64,
+    pub processed: AtomicU64,
+    pub dropped: AtomicU64, // Captured but replaced before the tracker could process them
+    pub sent: AtomicU64,
+    pub last_latency_us: AtomicU64, // From capture to the pose being sent to opentrack
+    pub total_latency_us: AtomicU64,
+}
+
+// A consumer of the mailbox, each one keeps track of the frames it has already seen
+pub struct FrameSubscription {
+    pub mailbox: FrameMailbox,
+    pub last_seq: u64,
+    pub count_frames: bool, // Only the tracker counts processed/dropped frames, not the preview
+}
@@ -1,9 +1,17 @@
 pub mod app;
 pub mod camera;
 pub mod data;
+pub mod frame;
+pub mod mailbox;
+pub mod model;
 pub mod network;
 pub mod pose;
+pub mod preview_server;
+pub mod recorder;
 pub mod release;
+pub mod signature;
+pub mod source;
 pub mod state;
 pub mod tddfa;
+pub mod tracking;
 pub mod face;
\ No newline at end of file
@@ -0,0 +1,27 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use super::data::Jsondata;
+
+// Describes a model of the models directory, saved as `manifest.json` in its folder
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ModelManifest {
+    pub input_size: i32,        // Width and height of the landmark model input
+    pub param_count: usize, // 62 for the bundled model, 72 and 141 for the larger 3DDFA variants
+    pub landmark_model: String, // Paths are relative to the folder
+    pub data: String,       // Mean, std and bases of the parameters
+    #[serde(default)]
+    pub face_detector: Option<String>, // The bundled one is used when not set
+    #[serde(default)]
+    pub checksums: HashMap<String, String>, // CRC32 of the files, in hexadecimal
+}
+
+// Validated models the tracker is created from
+pub struct ModelSet {
+    pub name: String,
+    pub input_size: i32,
+    pub landmark_model: Cow<'static, [u8]>,
+    pub data: Jsondata,
+    pub face_detector: Cow<'static, [u8]>,
+}
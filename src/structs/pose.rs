@@ -1,4 +1,10 @@
-use super::{face::FaceDetect, tddfa::Tddfa};
+use std::time::Instant;
+
+use super::{camera::CaptureRoi, face::FaceDetect, signature::FaceSignature, tddfa::Tddfa};
+use crate::{
+    enums::{face_policy::FacePolicy, tracking_state::TrackingState},
+    face::{Face, Rect},
+};
 
 pub struct ProcessHeadPose {
     pub tddfa: Tddfa,
@@ -6,6 +12,21 @@ pub struct ProcessHeadPose {
     pub pts_3d: Vec<Vec<f32>>,
     pub face_box: [f32; 4],
     pub first_iteration: bool,
-    pub param: [f32; 62],
+    pub param: Vec<f32>,
     pub roi_box: [f32; 4],
+    pub capture_roi: Option<CaptureRoi>, // Faces are only searched in this part of the frame
+    pub face_policy: FacePolicy,
+    pub detected_faces: Vec<Face>, // In the last frame, in full frame coordinates
+    pub tracked_face: Option<Rect>,
+    pub manual_target: Option<(f32, f32)>, // Point picked in the preview, in pixels
+    pub reidentify: bool, // Only reacquire the person that was tracked before being lost
+    pub signature: Option<FaceSignature>,
+    pub tracked_at: Option<Instant>, // Capture time of the last frame the person was tracked in
+    pub tracking_state: TrackingState,
+    pub detection_interval: u32, // Frames between two runs of the face detector while tracking
+    pub frames_since_detection: u32,
+    pub quality: f32, // How much the landmarks of the last frame can be trusted, from 0 to 1
+    pub min_quality: f32, // Frames below this are dropped, the previous pose is kept
+    pub rejected_frames: u32, // Dropped in a row
+    pub last_pose: [f32; 6],
 }
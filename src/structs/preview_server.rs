@@ -0,0 +1,23 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+};
+
+// Serves the annotated preview as an MJPEG stream, to watch the tracking from a browser or an OBS browser source
+pub struct PreviewServer {
+    pub address: SocketAddr,
+    pub slot: Arc<(Mutex<PreviewSlot>, Condvar)>, // Condvar wakes up the clients when a frame is published
+    pub clients: Arc<AtomicUsize>, // Frames are only encoded while someone is watching
+    pub keep_running: Arc<AtomicBool>,
+    pub server_thread: Option<thread::JoinHandle<()>>,
+}
+
+// Latest annotated frame, already encoded as JPEG and shared by all the clients
+pub struct PreviewSlot {
+    pub jpeg: Arc<Vec<u8>>,
+    pub seq: u64, // 0 if no frame was published yet
+}
@@ -0,0 +1,35 @@
+use std::{fs::File, io::BufWriter, path::PathBuf, time::Instant};
+
+use opencv::{core::Size, videoio::VideoWriter};
+use serde::{Deserialize, Serialize};
+
+use super::camera::FrameTransform;
+
+// Writes the captured frames along with what the tracker made of them, so a session reported by a user can be replayed
+pub struct SessionRecorder {
+    pub video_path: PathBuf,
+    pub sidecar_path: PathBuf,
+    pub fps: f64,
+    pub writer: Option<(VideoWriter, Size)>, // Opened with the size of the first frame
+    pub sidecar: BufWriter<File>,
+    pub started_at: Instant,
+    pub frames_written: u64,
+}
+
+// First line of the JSONL sidecar
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SessionHeader {
+    pub frame_transform: FrameTransform, // Already applied to the recorded frames
+}
+
+// One line of the JSONL sidecar, for every frame written to the video
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SessionRecord {
+    pub frame: u64,   // Index of the frame in the video
+    pub seq: u64,     // Sequence number given by the mailbox, gaps are frames the tracker dropped
+    pub time_ms: f64, // Capture time since the start of the recording
+    pub face_box: [f32; 4],
+    pub roi_box: [f32; 4],
+    pub raw: [f32; 6],              // x, y, z, yaw, pitch, roll from single_iter
+    pub filtered: Option<[f32; 6]>, // Sent to OpenTrack, null when nothing was sent
+}
@@ -0,0 +1,8 @@
+use opencv::prelude::Mat;
+
+// Appearance of the tracked person, so that only the same person is reacquired after they were lost
+#[derive(Debug, Clone)]
+pub struct FaceSignature {
+    pub histogram: Mat,  // Hue/saturation histogram of the face box, normalized
+    pub shape: Vec<f32>, // Shape coefficients (alpha_shp), they depend on the person rather than on the pose
+}
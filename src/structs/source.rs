@@ -0,0 +1,75 @@
+use std::{
+    io::BufReader,
+    net::TcpStream,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use opencv::videoio::VideoCapture;
+
+#[cfg(test)]
+use crate::enums::synthetic_pattern::SyntheticPattern;
+use crate::{
+    enums::capture_backend::CaptureBackend,
+    source::FrameSource,
+    structs::camera::{CameraControls, CaptureFormat, FrameTransform},
+};
+
+// Frames coming from a webcam
+pub struct CameraSource {
+    pub cam: VideoCapture,
+    pub camera_id: String,
+    pub camera_index: i32, // Resolved from the id every time the camera is opened
+    pub camera_name: String,
+    pub backend: CaptureBackend,
+    pub capture_format: Option<CaptureFormat>, // Kept to negotiate the same format again when reopening
+    pub controls: CameraControls,
+}
+
+// Frames played back from a recorded video file (.mp4, .avi, etc.)
+pub struct VideoFileSource {
+    pub video: VideoCapture,
+    pub path: String,
+    pub frame_interval: Option<Duration>, // None plays the file as fast as it can be decoded
+    pub next_frame_at: Option<Instant>,
+    pub looping: bool,
+}
+
+// Frames read from an ordered directory of PNG/JPEG images
+pub struct ImageSequenceSource {
+    pub directory: String,
+    pub paths: Vec<PathBuf>,
+    pub position: usize,
+    pub frame_interval: Option<Duration>,
+    pub next_frame_at: Option<Instant>,
+    pub looping: bool,
+}
+
+// Frames streamed over HTTP as multipart JPEGs (MJPEG), e.g. by a phone running an IP camera app
+pub struct MjpegSource {
+    pub url: String,
+    pub stream: BufReader<TcpStream>,
+    pub boundary: String, // Separates the JPEG parts, without the leading "--"
+}
+
+// Frames streamed over RTSP (or HTTPS, RTMP, etc.), decoded by OpenCV's FFmpeg backend
+pub struct StreamSource {
+    pub stream: VideoCapture,
+    pub url: String,
+}
+
+// Any other source with its frames rotated and/or flipped
+pub struct TransformedSource {
+    pub source: Box<dyn FrameSource>,
+    pub transform: FrameTransform,
+}
+
+// Frames generated programmatically, used to test the pipeline without any camera or file
+#[cfg(test)]
+pub struct SyntheticSource {
+    pub pattern: SyntheticPattern,
+    pub width: i32,
+    pub height: i32,
+    pub frame_index: u64,
+    pub frame_count: Option<u64>, // None generates frames forever
+}
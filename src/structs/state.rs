@@ -1,19 +1,25 @@
 /// Saving state of the application
-use std::sync::{
-    atomic::{AtomicU32, Ordering},
-    Arc,
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
 };
 
 use crate::{
-    consts::APP_NAME,
+    camera::migrate_camera_key,
+    consts::{APP_NAME, BUILTIN_MODEL_NAME},
+    enums::{capture_backend::CaptureBackend, face_policy::FacePolicy, loss_policy::LossPolicy},
     structs::app::{AtomicF32, Config, HeadTracker},
 };
 
 use serde::{Deserialize, Serialize};
 
-use super::camera::ThreadedCamera;
+use super::camera::{CameraControls, CaptureFormat, CaptureRoi, FrameTransform, ThreadedCamera};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)] // * Settings missing from older config files fall back to their default values
 pub struct AppConfig {
     pub ip: String,
     pub port: String,
@@ -22,6 +28,26 @@ pub struct AppConfig {
     pub fps: u32,
     pub selected_camera: String,
     pub hide_camera: bool,
+    pub input_path: String,
+    pub playback_speed: f32,
+    pub capture_formats: HashMap<String, CaptureFormat>, // Keyed by camera
+    pub camera_controls: HashMap<String, CameraControls>, // Keyed by camera
+    pub capture_backend: CaptureBackend,
+    pub frame_transform: FrameTransform,
+    pub capture_roi: Option<CaptureRoi>,
+    pub preview_server: bool,
+    pub preview_port: String,
+    pub preview_lan: bool,
+    pub face_policy: FacePolicy,
+    pub reidentify: bool,
+    pub detection_threshold: f32,
+    pub nms_threshold: f32,
+    pub detection_interval: u32,
+    pub min_quality: f32,
+    pub loss_policy: LossPolicy,
+    pub loss_timeout: f32,
+    pub model: String,
+    pub auto_resume: bool,
 }
 
 // Default values are used when the config file is not found or when there is an error loading the config file
@@ -48,6 +74,27 @@ impl Default for AppConfig {
             },
 
             hide_camera: true,
+
+            input_path: String::new(),
+            playback_speed: 1.,
+            capture_formats: HashMap::new(),
+            camera_controls: HashMap::new(),
+            capture_backend: CaptureBackend::default(),
+            frame_transform: FrameTransform::default(),
+            capture_roi: None,
+            preview_server: false,
+            preview_port: "9090".to_string(),
+            preview_lan: false,
+            face_policy: FacePolicy::default(),
+            reidentify: true,
+            detection_threshold: 0.5,
+            nms_threshold: 0.3,
+            detection_interval: 1,
+            min_quality: 0.,
+            loss_policy: LossPolicy::default(),
+            loss_timeout: 2.,
+            model: BUILTIN_MODEL_NAME.to_string(),
+            auto_resume: true,
         }
     }
 }
@@ -63,13 +110,38 @@ impl HeadTracker {
             }
         };
 
-        let selected_camera = match self.camera_list.get(&cfg.selected_camera) {
-            Some(_) => cfg.selected_camera,
+        // Cameras used to be saved as "{name:<4} {index}", they are now saved by their id
+        // * A camera that isn't plugged in keeps its key, so it is picked again once it's back
+        let selected_camera = match migrate_camera_key(&cfg.selected_camera, &self.camera_list) {
+            Some(camera_id) => camera_id,
+            None if !cfg.selected_camera.is_empty() && cfg.selected_camera != "No Device Found" => {
+                cfg.selected_camera.clone()
+            }
             None => match self.camera_list.keys().next() {
                 Some(key) => key.clone(),
                 None => "No Device Found".to_string(),
             },
         };
+        let capture_formats = cfg
+            .capture_formats
+            .into_iter()
+            .map(|(key, format)| {
+                (
+                    migrate_camera_key(&key, &self.camera_list).unwrap_or(key),
+                    format,
+                )
+            })
+            .collect();
+        let camera_controls = cfg
+            .camera_controls
+            .into_iter()
+            .map(|(key, controls)| {
+                (
+                    migrate_camera_key(&key, &self.camera_list).unwrap_or(key),
+                    controls,
+                )
+            })
+            .collect();
 
         Config {
             min_cutoff: Arc::new(AtomicF32::new(cfg.min_cutoff)),
@@ -82,8 +154,45 @@ impl HeadTracker {
 
             selected_camera,
             hide_camera: cfg.hide_camera,
+
+            input_path: cfg.input_path,
+            playback_speed: cfg.playback_speed,
+            capture_formats,
+            camera_controls,
+            capture_backend: cfg.capture_backend,
+            frame_transform: cfg.frame_transform,
+            capture_roi: Arc::new(Mutex::new(cfg.capture_roi)),
+            preview_server: cfg.preview_server,
+            preview_port: cfg.preview_port,
+            preview_lan: cfg.preview_lan,
+            face_policy: cfg.face_policy,
+            reidentify: cfg.reidentify,
+            detection_threshold: Arc::new(AtomicF32::new(cfg.detection_threshold)),
+            nms_threshold: Arc::new(AtomicF32::new(cfg.nms_threshold)),
+            detection_interval: Arc::new(AtomicU32::new(cfg.detection_interval)),
+            min_quality: Arc::new(AtomicF32::new(cfg.min_quality)),
+            loss_policy: cfg.loss_policy,
+            loss_timeout: Arc::new(AtomicF32::new(cfg.loss_timeout)),
+            model: cfg.model,
+            auto_resume: cfg.auto_resume,
         }
     }
+    // Querying the modes supported by the selected camera, shown in the GUI
+    pub fn refresh_camera_formats(&mut self) {
+        self.camera_formats = match self.camera_list.get(&self.config.selected_camera) {
+            Some(camera) if camera.index >= 0 => {
+                match ThreadedCamera::get_supported_formats(camera.index) {
+                    Ok(formats) => formats,
+                    Err(e) => {
+                        tracing::error!("{}", e);
+                        vec![]
+                    }
+                }
+            }
+            _ => vec![],
+        };
+    }
+
     pub fn save_config(&self) {
         let config = AppConfig {
             ip: self.config.ip.clone(),
@@ -93,6 +202,26 @@ impl HeadTracker {
             fps: self.config.fps.load(Ordering::SeqCst),
             selected_camera: self.config.selected_camera.clone(),
             hide_camera: self.config.hide_camera,
+            input_path: self.config.input_path.clone(),
+            playback_speed: self.config.playback_speed,
+            capture_formats: self.config.capture_formats.clone(),
+            camera_controls: self.config.camera_controls.clone(),
+            capture_backend: self.config.capture_backend.clone(),
+            frame_transform: self.config.frame_transform,
+            capture_roi: *self.config.capture_roi.lock().unwrap(),
+            preview_server: self.config.preview_server,
+            preview_port: self.config.preview_port.clone(),
+            preview_lan: self.config.preview_lan,
+            face_policy: self.config.face_policy,
+            reidentify: self.config.reidentify,
+            detection_threshold: self.config.detection_threshold.load(Ordering::SeqCst),
+            nms_threshold: self.config.nms_threshold.load(Ordering::SeqCst),
+            detection_interval: self.config.detection_interval.load(Ordering::SeqCst),
+            min_quality: self.config.min_quality.load(Ordering::SeqCst),
+            loss_policy: self.config.loss_policy,
+            loss_timeout: self.config.loss_timeout.load(Ordering::SeqCst),
+            model: self.config.model.clone(),
+            auto_resume: self.config.auto_resume,
         };
 
         match confy::store(APP_NAME, "config", config) {
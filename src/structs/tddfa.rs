@@ -3,11 +3,20 @@ use onnxruntime::{
     session::Session,
 };
 
+// Where the pose, shape and expression coefficients are in the parameters of a 3DDFA model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParamLayout {
+    pub trans_dim: usize, // 3x4 pose matrix
+    pub shape_dim: usize,
+    pub exp_dim: usize,
+}
+
 pub struct Tddfa {
     pub landmark_model: Session<'static>,
     pub size: i32,
-    pub mean_array: [f32; 62],
-    pub std_array: [f32; 62],
+    pub layout: ParamLayout,
+    pub mean_array: Vec<f32>,
+    pub std_array: Vec<f32>,
     pub u_base_array: ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
     pub w_shp_base_array: ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
     pub w_exp_base_array: ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
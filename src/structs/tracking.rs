@@ -0,0 +1,8 @@
+use std::time::Instant;
+
+// Last tracked pose, sent in place of the missing ones while the face is lost
+#[derive(Debug, Default)]
+pub struct PoseHold {
+    pub last_pose: Option<[f32; 6]>,
+    pub lost_at: Option<Instant>, // Capture time of the first frame without the face
+}
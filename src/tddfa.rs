@@ -2,12 +2,16 @@
 /// Python source - https://github.com/cleardusk/3DDFA_V2/blob/master/TDDFA.py
 // Importing Modules
 use crate::{
-    consts::{DATA, MODEL},
+    consts::{LANDMARK_COORDINATES, QUALITY_MAX_PARAM_Z},
     enums::crop_policy::CropPolicy,
-    structs::{data::Jsondata, tddfa::Tddfa},
+    structs::{
+        data::Jsondata,
+        model::ModelSet,
+        tddfa::{ParamLayout, Tddfa},
+    },
     utils::{
         common::get_ndarray,
-        image::crop_img,
+        image::{crop_img, to_bgr},
         tddfa::{
             parse_param, parse_roi_box_from_bbox, parse_roi_box_from_landmark, similar_transform,
         },
@@ -16,13 +20,13 @@ use crate::{
 
 use onnxruntime::{
     environment::Environment,
-    ndarray::{arr1, arr2, s, Array4, ArrayBase, Dim, Order, OwnedRepr},
+    ndarray::{arr2, s, Array1, Array4, ArrayBase, Axis, Dim, Order, OwnedRepr},
     tensor::OrtOwnedTensor,
     GraphOptimizationLevel,
 };
 use std::ops::Deref;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use once_cell::sync::Lazy;
 use opencv::{
     core::{Size, Vec3b},
@@ -30,8 +34,55 @@ use opencv::{
     prelude::{Mat, MatTraitConstManual},
 };
 
+impl ParamLayout {
+    // Read from the bases of the model, 40 shape and 10 expression coefficients for the bundled one
+    pub fn from_data(data: &Jsondata) -> Result<Self> {
+        let columns = |base: &[Vec<f32>], name: &str| -> Result<usize> {
+            match base.first().map(Vec::len) {
+                Some(columns)
+                    if columns > 0
+                        && base.len() == LANDMARK_COORDINATES
+                        && base.iter().all(|row| row.len() == columns) =>
+                {
+                    Ok(columns)
+                }
+                _ => {
+                    bail!("The {name} base should be {LANDMARK_COORDINATES} rows of the same size")
+                }
+            }
+        };
+
+        if columns(&data.u_base, "mean shape")? != 1 {
+            bail!("The mean shape base should have a single column");
+        }
+        let layout = Self {
+            trans_dim: 12,
+            shape_dim: columns(&data.w_shp_base, "shape")?,
+            exp_dim: columns(&data.w_exp_base, "expression")?,
+        };
+
+        let param_count = layout.param_count();
+        if data.mean.len() != param_count || data.std.len() != param_count {
+            bail!("The mean and std should have {param_count} values, one per parameter");
+        }
+        Ok(layout)
+    }
+
+    pub fn param_count(&self) -> usize {
+        self.trans_dim + self.shape_dim + self.exp_dim
+    }
+
+    pub fn shape<'a>(&self, param: &'a [f32]) -> &'a [f32] {
+        &param[self.trans_dim..self.trans_dim + self.shape_dim]
+    }
+
+    pub fn expression<'a>(&self, param: &'a [f32]) -> &'a [f32] {
+        &param[self.trans_dim + self.shape_dim..self.param_count()]
+    }
+}
+
 impl Tddfa {
-    pub fn new(size: i32) -> Result<Self> {
+    pub fn new(models: &ModelSet) -> Result<Self> {
         static ENVIRONMENT: Lazy<Environment> = Lazy::new(|| {
             match Environment::builder()
                 .with_name("Landmark Detection")
@@ -50,22 +101,27 @@ impl Tddfa {
             .new_session_builder()?
             .with_optimization_level(GraphOptimizationLevel::All)?
             .with_number_threads(1)?
-            .with_model_from_memory(MODEL)?;
-
-        let data = serde_json::from_slice::<Jsondata>(DATA)?;
+            .with_model_from_memory(models.landmark_model.as_ref())?;
 
-        let mean_array: [f32; 62] = data.mean.as_slice().try_into()?;
-        let std_array: [f32; 62] = data.std.as_slice().try_into()?;
+        let data = &models.data;
+        let layout = ParamLayout::from_data(data)?;
 
-        let u_base_array = get_ndarray(data.u_base, (204, 1));
-        let w_shp_base_array = get_ndarray(data.w_shp_base, (204, 40));
-        let w_exp_base_array = get_ndarray(data.w_exp_base, (204, 10));
+        let u_base_array = get_ndarray(data.u_base.clone(), (LANDMARK_COORDINATES, 1));
+        let w_shp_base_array = get_ndarray(
+            data.w_shp_base.clone(),
+            (LANDMARK_COORDINATES, layout.shape_dim),
+        );
+        let w_exp_base_array = get_ndarray(
+            data.w_exp_base.clone(),
+            (LANDMARK_COORDINATES, layout.exp_dim),
+        );
 
         Ok(Self {
             landmark_model,
-            size,
-            mean_array,
-            std_array,
+            size: models.input_size,
+            layout,
+            mean_array: data.mean.clone(),
+            std_array: data.std.clone(),
             u_base_array,
             w_shp_base_array,
             w_exp_base_array,
@@ -97,6 +153,9 @@ impl Tddfa {
             imgproc::INTER_LINEAR, //*INTER_AREA, // https://stackoverflow.com/a/51042104 | Speed -> https://stackoverflow.com/a/44278268
         )?; // ! Error handling here
 
+        // Mono (IR) and BGRA frames, converted after resizing since it's cheaper on the smaller image
+        let resized_frame = to_bgr(&resized_frame)?;
+
         let vec = Mat::data_typed::<Vec3b>(&resized_frame)?;
         // .?("Unable to convert the image to vector");
 
@@ -117,7 +176,7 @@ impl Tddfa {
         face_box: [f32; 4],
         ver: &[Vec<f32>],
         crop_policy: CropPolicy,
-    ) -> Result<([f32; 62], [f32; 4])> {
+    ) -> Result<(Vec<f32>, [f32; 4])> {
         let roi_box = match crop_policy {
             CropPolicy::Box => parse_roi_box_from_bbox(face_box),
             CropPolicy::Landmark => parse_roi_box_from_landmark(ver),
@@ -128,35 +187,42 @@ impl Tddfa {
 
         // Inference
         let param: Vec<OrtOwnedTensor<f32, _>> = self.landmark_model.run(model_input)?;
-        let param: [f32; 62] = match param[0].as_slice() {
-            Some(slice) => slice.try_into()?,
+        let param = match param[0].as_slice() {
+            Some(slice) => slice,
             None => {
                 tracing::error!("Unable to convert the tensor to slice param");
                 return Err(anyhow!("Unable to convert the tensor to slice param"));
             }
         };
+        if param.len() != self.layout.param_count() {
+            return Err(anyhow!(
+                "The landmark model gives {} parameters instead of {}",
+                param.len(),
+                self.layout.param_count()
+            ));
+        }
 
         // Postprocessing - Rescaling the output by multiplying with standard deviation and adding mean
-        let processed_param = arr1(&param) * arr1(&self.std_array) + arr1(&self.mean_array);
-        let processed_param: [f32; 62] = match processed_param.as_slice() {
-            Some(slice) => slice.try_into()?,
-            None => {
-                tracing::error!("Unable to convert the tensor to slice processed_param");
-                return Err(anyhow!(
-                    "Unable to convert the tensor to slice processed_param"
-                ));
-            }
-        };
+        let processed_param = param
+            .iter()
+            .zip(&self.std_array)
+            .zip(&self.mean_array)
+            .map(|((value, std), mean)| value * std + mean)
+            .collect::<Vec<f32>>();
 
         Ok((processed_param, roi_box))
     }
 
-    pub fn recon_vers(&self, param: [f32; 62], roi_box: [f32; 4]) -> Vec<Vec<f32>> {
-        let (r, offset, alpha_shp, alpha_exp) = parse_param(&param);
+    pub fn recon_vers(&self, param: &[f32], roi_box: [f32; 4]) -> Vec<Vec<f32>> {
+        let (r, offset, alpha_shp, alpha_exp) = parse_param(param, &self.layout);
+
+        // Coefficients as column vectors
+        let alpha_shp = Array1::from(alpha_shp.to_vec()).insert_axis(Axis(1));
+        let alpha_exp = Array1::from(alpha_exp.to_vec()).insert_axis(Axis(1));
 
         let pts3d = &self.u_base_array
-            + (&self.w_shp_base_array.dot(&arr2(&alpha_shp)))
-            + (&self.w_exp_base_array.dot(&arr2(&alpha_exp)));
+            + (&self.w_shp_base_array.dot(&alpha_shp))
+            + (&self.w_exp_base_array.dot(&alpha_exp));
 
         let pts3d = match pts3d.to_shape(((3, 68), Order::ColumnMajor)) {
             Ok(pts3d) => pts3d,
@@ -174,6 +240,21 @@ impl Tddfa {
         ];
         similar_transform(vec_pts_3d, roi_box, self.size as f32)
     }
+
+    // From 1 when the shape and expression look like the training faces, to 0 when they are far off
+    // * Hands or blurred frames give parameters many standard deviations away from the mean
+    pub fn param_plausibility(&self, param: &[f32]) -> f32 {
+        let trans_dim = self.layout.trans_dim;
+        let squared_z = param[trans_dim..]
+            .iter()
+            .zip(&self.mean_array[trans_dim..])
+            .zip(&self.std_array[trans_dim..])
+            .map(|((value, mean), std)| ((value - mean) / std).powi(2))
+            .sum::<f32>();
+        let rms_z = (squared_z / (param.len() - trans_dim) as f32).sqrt();
+
+        (1. - (rms_z - 1.) / (QUALITY_MAX_PARAM_Z - 1.)).clamp(0., 1.)
+    }
 }
 
 #[test]
@@ -181,9 +262,7 @@ impl Tddfa {
 pub fn test() -> Result<()> {
     use opencv::core::{Scalar, CV_8UC3};
 
-    let size = 120;
-
-    let mut bfm = Tddfa::new(size)?;
+    let mut bfm = Tddfa::new(&ModelSet::builtin()?)?;
 
     let frame = Mat::new_rows_cols_with_default(120, 120, CV_8UC3, Scalar::new(255., 0., 0., 0.))?;
 
@@ -194,7 +273,7 @@ pub fn test() -> Result<()> {
         &[vec![1., 2., 3.], vec![4., 5., 6.], vec![7., 8., 9.]],
         CropPolicy::Box,
     )?;
-    let pts_3d = bfm.recon_vers(param, roi_box);
+    let pts_3d = bfm.recon_vers(&param, roi_box);
 
     let (param, roi_box) = bfm.run(&frame, face_box, &pts_3d, CropPolicy::Landmark)?;
 
@@ -205,7 +284,78 @@ pub fn test() -> Result<()> {
     //     39., 40., 41., 42., 43., 44., 45., 46., 47., 48., 49., 50., 51., 52., 53., 54., 55., 56.,
     //     57., 58., 59., 60., 61., 62.,
     // ];
-    // let pts_3d = bfm.recon_vers(param, roi_box);
+    // let pts_3d = bfm.recon_vers(&param, roi_box);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_input_formats() -> Result<()> {
+    use opencv::core::{Scalar, CV_16UC1, CV_8UC1, CV_8UC3, CV_8UC4};
+
+    let mut bfm = Tddfa::new(&ModelSet::builtin()?)?;
+    let face_box = [30., 30., 90., 90.];
+    let ver = [vec![1., 2., 3.], vec![4., 5., 6.], vec![7., 8., 9.]];
+
+    // A gray frame gives the same result whether it comes as BGR, BGRA or mono
+    let bgr_frame = Mat::new_rows_cols_with_default(120, 120, CV_8UC3, Scalar::all(100.))?;
+    let (expected, _) = bfm.run(&bgr_frame, face_box, &ver, CropPolicy::Box)?;
+
+    for typ in [CV_8UC1, CV_8UC4] {
+        let frame = Mat::new_rows_cols_with_default(120, 120, typ, Scalar::all(100.))?;
+        let (param, _) = bfm.run(&frame, face_box, &ver, CropPolicy::Box)?;
+        assert_eq!(param, expected);
+    }
+
+    // 16-bit frames are stretched, only checking they go through
+    let frame = Mat::new_rows_cols_with_default(120, 120, CV_16UC1, Scalar::all(4000.))?;
+    bfm.run(&frame, face_box, &ver, CropPolicy::Box)?;
+
+    Ok(())
+}
+
+#[test]
+pub fn test_param_plausibility() -> Result<()> {
+    let bfm = Tddfa::new(&ModelSet::builtin()?)?;
+
+    // The mean face is as plausible as it gets, and one far away from it is not
+    assert_eq!(bfm.param_plausibility(&bfm.mean_array), 1.);
+    let mut param = bfm.mean_array.clone();
+    for (value, std) in param[12..].iter_mut().zip(&bfm.std_array[12..]) {
+        *value += 5. * std;
+    }
+    assert_eq!(bfm.param_plausibility(&param), 0.);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_param_layout() -> Result<()> {
+    let data = |shape_dim: usize, exp_dim: usize, param_count: usize| Jsondata {
+        mean: vec![0.; param_count],
+        std: vec![1.; param_count],
+        u_base: vec![vec![0.; 1]; LANDMARK_COORDINATES],
+        w_shp_base: vec![vec![0.; shape_dim]; LANDMARK_COORDINATES],
+        w_exp_base: vec![vec![0.; exp_dim]; LANDMARK_COORDINATES],
+    };
+
+    // The bundled model and the larger variants
+    for (shape_dim, exp_dim) in [(40, 10), (40, 20), (100, 29)] {
+        let layout = ParamLayout::from_data(&data(shape_dim, exp_dim, 12 + shape_dim + exp_dim))?;
+        assert_eq!(layout.param_count(), 12 + shape_dim + exp_dim);
+
+        let param = (0..layout.param_count())
+            .map(|i| i as f32)
+            .collect::<Vec<f32>>();
+        assert_eq!(layout.shape(&param), &param[12..12 + shape_dim]);
+        assert_eq!(layout.expression(&param), &param[12 + shape_dim..]);
+    }
+
+    // Statistics that don't match the bases
+    assert!(ParamLayout::from_data(&data(40, 10, 72)).is_err());
+    let mut ragged = data(40, 10, 62);
+    ragged.w_shp_base[3].pop();
+    assert!(ParamLayout::from_data(&ragged).is_err());
 
     Ok(())
 }
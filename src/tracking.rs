@@ -0,0 +1,130 @@
+/// What is sent to opentrack while the face is lost, so the in-game camera doesn't glide back to the center
+use crate::{
+    consts::LOSS_EASE_DURATION,
+    enums::{loss_policy::LossPolicy, tracking_state::TrackingState},
+    structs::{frame::PoseSample, tracking::PoseHold},
+};
+
+use std::{fmt::Display, time::Duration};
+
+impl LossPolicy {
+    pub const ALL: [LossPolicy; 3] = [
+        LossPolicy::HoldLast,
+        LossPolicy::HoldThenCenter,
+        LossPolicy::SendNothing,
+    ];
+}
+
+impl Display for LossPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LossPolicy::HoldLast => write!(f, "Hold the last pose"),
+            LossPolicy::HoldThenCenter => write!(f, "Hold, then go back to the center"),
+            LossPolicy::SendNothing => write!(f, "Send nothing"),
+        }
+    }
+}
+
+impl Display for TrackingState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrackingState::Searching => write!(f, "Searching for a face"),
+            TrackingState::Tracking => write!(f, "Tracking"),
+            TrackingState::Lost => write!(f, "Face lost"),
+        }
+    }
+}
+
+impl PoseHold {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Pose to send for this sample, None when nothing should be sent
+    pub fn apply(
+        &mut self,
+        state: TrackingState,
+        sample: PoseSample,
+        policy: LossPolicy,
+        timeout: Duration,
+    ) -> Option<PoseSample> {
+        if state == TrackingState::Tracking {
+            self.last_pose = Some(sample.data);
+            self.lost_at = None;
+            return Some(sample);
+        }
+
+        let lost_at = *self.lost_at.get_or_insert(sample.captured_at);
+        let lost_for = sample.captured_at.saturating_duration_since(lost_at);
+        // * The center is held until a face is found for the first time
+        let held = self.last_pose.unwrap_or_default();
+
+        let data = match policy {
+            LossPolicy::SendNothing => return None,
+            LossPolicy::HoldLast => held,
+            LossPolicy::HoldThenCenter => {
+                let progress = (lost_for.saturating_sub(timeout).as_secs_f32()
+                    / LOSS_EASE_DURATION.as_secs_f32())
+                .min(1.);
+                held.map(|value| value * (1. - progress))
+            }
+        };
+
+        Some(PoseSample { data, ..sample })
+    }
+}
+
+#[test]
+pub fn test_pose_hold() {
+    use std::time::Instant;
+
+    let start = Instant::now();
+    let sample = |data: [f32; 6], after: Duration| PoseSample {
+        data,
+        seq: 0,
+        captured_at: start + after,
+    };
+    let timeout = Duration::from_secs(2);
+
+    let mut hold = PoseHold::new();
+    let tracked = sample([10.; 6], Duration::ZERO);
+    assert_eq!(
+        hold.apply(
+            TrackingState::Tracking,
+            tracked,
+            LossPolicy::HoldThenCenter,
+            timeout
+        ),
+        Some(tracked)
+    );
+
+    // The last pose is held until the timeout, then eased back to the center
+    let mut lost = |after: f32, policy| {
+        hold.apply(
+            TrackingState::Lost,
+            sample([0.; 6], Duration::from_secs_f32(after)),
+            policy,
+            timeout,
+        )
+        .map(|sample| sample.data[0])
+    };
+    assert_eq!(lost(0.1, LossPolicy::HoldThenCenter), Some(10.));
+    assert_eq!(lost(1.9, LossPolicy::HoldThenCenter), Some(10.));
+    assert!((lost(2.6, LossPolicy::HoldThenCenter).unwrap() - 5.).abs() < 1e-3);
+    assert_eq!(lost(4., LossPolicy::HoldThenCenter), Some(0.));
+    assert_eq!(lost(4., LossPolicy::HoldLast), Some(10.));
+    assert_eq!(lost(4., LossPolicy::SendNothing), None);
+
+    // Nothing was tracked yet, the center is sent
+    let mut hold = PoseHold::new();
+    let searching = sample([3.; 6], Duration::ZERO);
+    assert_eq!(
+        hold.apply(
+            TrackingState::Searching,
+            searching,
+            LossPolicy::HoldLast,
+            timeout
+        ),
+        Some(sample([0.; 6], Duration::ZERO))
+    );
+}
@@ -58,7 +58,7 @@ fn matrix2angle(r: &[[f32; 3]]) -> (f32, f32, f32) {
     (x, y, z)
 }
 
-pub fn calc_pose(param: &[f32; 62]) -> ([[f32; 4]; 3], [f32; 3]) {
+pub fn calc_pose(param: &[f32]) -> ([[f32; 4]; 3], [f32; 3]) {
     let p = [
         [param[0], param[1], param[2], param[3]],
         [param[4], param[5], param[6], param[7]],
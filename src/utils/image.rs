@@ -1,8 +1,11 @@
 /// Utility function for processing image
 /// Python source - https://github.com/cleardusk/3DDFA/blob/d5c1f6a647a89070b1f9ea4e88c910b743a1a87a/utils/inference.py#L20
-use opencv::core::{Mat, Rect};
+use opencv::core::{self, Mat, Rect, CV_16UC1, CV_8U, CV_8UC1, CV_8UC3, CV_8UC4};
+use opencv::imgproc;
 use opencv::prelude::MatTraitConst;
 
+use crate::{enums::rotation::Rotation, structs::camera::FrameTransform};
+
 pub fn crop_img(img: &Mat, roi_box: &[f32; 4]) -> Result<Mat, opencv::Error> {
     let h = img.size()?.height;
     let w = img.size()?.width;
@@ -48,6 +51,64 @@ pub fn crop_img(img: &Mat, roi_box: &[f32; 4]) -> Result<Mat, opencv::Error> {
     }
 }
 
+// Converts the frame to the 8-bit, 3-channel BGR layout the models expect
+// * Mono frames come from IR cameras, 16-bit ones are stretched to their own min/max since they rarely use all 16 bits
+pub fn to_bgr(frame: &Mat) -> Result<Mat, opencv::Error> {
+    let mut bgr_frame = Mat::default();
+
+    match frame.typ() {
+        CV_8UC3 => bgr_frame = frame.try_clone()?,
+        CV_8UC1 => imgproc::cvt_color(frame, &mut bgr_frame, imgproc::COLOR_GRAY2BGR, 0)?,
+        CV_8UC4 => imgproc::cvt_color(frame, &mut bgr_frame, imgproc::COLOR_BGRA2BGR, 0)?,
+        CV_16UC1 => {
+            let mut gray_frame = Mat::default();
+            core::normalize(
+                frame,
+                &mut gray_frame,
+                0.,
+                255.,
+                core::NORM_MINMAX,
+                CV_8U,
+                &core::no_array(),
+            )?;
+            imgproc::cvt_color(&gray_frame, &mut bgr_frame, imgproc::COLOR_GRAY2BGR, 0)?;
+        }
+        typ => {
+            return Err(opencv::Error::new(
+                core::StsUnsupportedFormat,
+                format!("Unsupported frame type {}", core::type_to_string(typ)?),
+            ))
+        }
+    }
+
+    Ok(bgr_frame)
+}
+
+// Rotates clockwise, then mirrors and flips the frame
+pub fn transform_frame(frame: &Mat, transform: &FrameTransform) -> Result<Mat, opencv::Error> {
+    let mut rotated_frame = Mat::default();
+    match transform.rotation {
+        Rotation::Rotate0 => rotated_frame = frame.try_clone()?,
+        Rotation::Rotate90 => core::rotate(frame, &mut rotated_frame, core::ROTATE_90_CLOCKWISE)?,
+        Rotation::Rotate180 => core::rotate(frame, &mut rotated_frame, core::ROTATE_180)?,
+        Rotation::Rotate270 => {
+            core::rotate(frame, &mut rotated_frame, core::ROTATE_90_COUNTERCLOCKWISE)?
+        }
+    }
+
+    // * Flip code is 1 around the y axis, 0 around the x axis and -1 around both
+    let flip_code = match (transform.mirror, transform.flip) {
+        (false, false) => return Ok(rotated_frame),
+        (true, false) => 1,
+        (false, true) => 0,
+        (true, true) => -1,
+    };
+    let mut flipped_frame = Mat::default();
+    core::flip(&rotated_frame, &mut flipped_frame, flip_code)?;
+
+    Ok(flipped_frame)
+}
+
 #[test]
 fn test_crop_img() -> Result<(), opencv::Error> {
     use opencv::{
@@ -70,3 +131,103 @@ fn test_crop_img() -> Result<(), opencv::Error> {
 
     Ok(())
 }
+
+#[test]
+fn test_to_bgr() -> Result<(), opencv::Error> {
+    use opencv::{
+        core::{Scalar, Vec3b, CV_32FC1},
+        prelude::MatTrait,
+    };
+
+    let pixel = |frame: &Mat| -> Result<Vec3b, opencv::Error> {
+        assert_eq!(frame.typ(), CV_8UC3);
+        Ok(*frame.at_2d::<Vec3b>(0, 0)?)
+    };
+
+    // BGR is left as it is
+    let frame = Mat::new_rows_cols_with_default(4, 4, CV_8UC3, Scalar::new(10., 20., 30., 0.))?;
+    assert_eq!(pixel(&to_bgr(&frame)?)?, Vec3b::from([10, 20, 30]));
+
+    // 8-bit mono, the gray value is copied to the 3 channels
+    let frame = Mat::new_rows_cols_with_default(4, 4, CV_8UC1, Scalar::all(77.))?;
+    assert_eq!(pixel(&to_bgr(&frame)?)?, Vec3b::from([77, 77, 77]));
+
+    // BGRA, the alpha channel is dropped
+    let frame = Mat::new_rows_cols_with_default(4, 4, CV_8UC4, Scalar::new(10., 20., 30., 40.))?;
+    assert_eq!(pixel(&to_bgr(&frame)?)?, Vec3b::from([10, 20, 30]));
+
+    // 16-bit mono using 10 bits, stretched to the full 8-bit range
+    let mut frame = Mat::new_rows_cols_with_default(4, 4, CV_16UC1, Scalar::all(0.))?;
+    *frame.at_2d_mut::<u16>(3, 3)? = 1023;
+    let bgr_frame = to_bgr(&frame)?;
+    assert_eq!(pixel(&bgr_frame)?, Vec3b::from([0, 0, 0]));
+    assert_eq!(
+        *bgr_frame.at_2d::<Vec3b>(3, 3)?,
+        Vec3b::from([255, 255, 255])
+    );
+
+    // Anything else is an error
+    let frame = Mat::new_rows_cols_with_default(4, 4, CV_32FC1, Scalar::all(0.5))?;
+    assert!(to_bgr(&frame).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_transform_frame() -> Result<(), opencv::Error> {
+    use opencv::{
+        core::{Scalar, Vec3b},
+        prelude::MatTrait,
+    };
+
+    // 2x3 frame with a single white pixel in the top left corner
+    let mut frame = Mat::new_rows_cols_with_default(2, 3, CV_8UC3, Scalar::all(0.))?;
+    *frame.at_2d_mut::<Vec3b>(0, 0)? = Vec3b::from([255, 255, 255]);
+
+    // Where the white pixel ends up, and the size of the transformed frame
+    let white_pixel = |transform: FrameTransform| -> Result<(i32, i32, i32, i32), opencv::Error> {
+        let frame = transform_frame(&frame, &transform)?;
+        for row in 0..frame.rows() {
+            for col in 0..frame.cols() {
+                if frame.at_2d::<Vec3b>(row, col)?[0] == 255 {
+                    return Ok((row, col, frame.rows(), frame.cols()));
+                }
+            }
+        }
+        panic!("White pixel is missing");
+    };
+
+    let rotated = |rotation: Rotation| FrameTransform {
+        rotation,
+        ..Default::default()
+    };
+    assert_eq!(white_pixel(FrameTransform::default())?, (0, 0, 2, 3));
+    assert_eq!(white_pixel(rotated(Rotation::Rotate90))?, (0, 1, 3, 2));
+    assert_eq!(white_pixel(rotated(Rotation::Rotate180))?, (1, 2, 2, 3));
+    assert_eq!(white_pixel(rotated(Rotation::Rotate270))?, (2, 0, 3, 2));
+
+    let mirrored = FrameTransform {
+        mirror: true,
+        ..Default::default()
+    };
+    assert_eq!(white_pixel(mirrored)?, (0, 2, 2, 3));
+
+    let flipped = FrameTransform {
+        flip: true,
+        ..Default::default()
+    };
+    assert_eq!(white_pixel(flipped)?, (1, 0, 2, 3));
+
+    // Mirroring and flipping is the same as rotating by 180 degrees
+    let both = FrameTransform {
+        mirror: true,
+        flip: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        white_pixel(both)?,
+        white_pixel(rotated(Rotation::Rotate180))?
+    );
+
+    Ok(())
+}
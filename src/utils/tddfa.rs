@@ -3,24 +3,14 @@
 /// Python source - https://github.com/cleardusk/3DDFA_V2/blob/master/utils/tddfa_util.py
 /// https://github.com/cleardusk/3DDFA_V2/blob/master/utils/functions.py#L65
 use crate::enums::extreme::Extreme;
+use crate::structs::tddfa::ParamLayout;
 use crate::utils::common::get_extreme_value;
 
-pub fn parse_param(
-    param: &[f32; 62],
-) -> ([[f32; 3]; 3], [[f32; 1]; 3], [[f32; 1]; 40], [[f32; 1]; 10]) {
-    // TODO: Can use type alias/defininations to improve code redability.
-    let n = param.len();
-
-    let (trans_dim, shape_dim, _) = match n {
-        62 => (12, 40, 10),
-        72 => (12, 40, 20),
-        141 => (12, 100, 29),
-        invalid_size => {
-            tracing::error!("Undefined templated param parsing rule : {invalid_size}");
-            panic!()
-        }
-    };
-
+// Pose matrix, offset, shape and expression coefficients, the sizes of the last two depend on the model
+pub fn parse_param<'a>(
+    param: &'a [f32],
+    layout: &ParamLayout,
+) -> ([[f32; 3]; 3], [[f32; 1]; 3], &'a [f32], &'a [f32]) {
     let r_ = [
         [param[0], param[1], param[2], param[3]],
         [param[4], param[5], param[6], param[7]],
@@ -35,17 +25,7 @@ pub fn parse_param(
 
     let offset = [[r_[0][3]], [r_[1][3]], [r_[2][3]]];
 
-    let mut alpha_shp = [[0.0; 1]; 40];
-    for i in 0..40 {
-        alpha_shp[i][0] = param[trans_dim + i];
-    }
-
-    let mut alpha_exp = [[0.0; 1]; 10];
-    for i in 0..10 {
-        alpha_exp[i][0] = param[trans_dim + shape_dim + i];
-    }
-
-    (r, offset, alpha_shp, alpha_exp)
+    (r, offset, layout.shape(param), layout.expression(param))
 }
 
 pub fn similar_transform(mut pts3d: Vec<Vec<f32>>, roi_box: [f32; 4], size: f32) -> Vec<Vec<f32>> {
@@ -133,76 +113,32 @@ mod tests {
 
     #[test]
     fn test_parse_param() {
-        let param = [
-            0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0, 13.0, 14.0, 15.0,
-            16.0, 17.0, 18.0, 19.0, 20.0, 21.0, 22.0, 23.0, 24.0, 25.0, 26.0, 27.0, 28.0, 29.0,
-            30.0, 31.0, 32.0, 33.0, 34.0, 35.0, 36.0, 37.0, 38.0, 39.0, 40.0, 41.0, 42.0, 43.0,
-            44.0, 45.0, 46.0, 47.0, 48.0, 49.0, 50.0, 51.0, 52.0, 53.0, 54.0, 55.0, 56.0, 57.0,
-            58.0, 59.0, 60.0, 61.0,
-        ];
-
-        let result = parse_param(&param);
-
-        let expected = (
-            [[0.0, 1.0, 2.0], [4.0, 5.0, 6.0], [8.0, 9.0, 10.0]],
-            [[3.0], [7.0], [11.0]],
-            [
-                [12.0],
-                [13.0],
-                [14.0],
-                [15.0],
-                [16.0],
-                [17.0],
-                [18.0],
-                [19.0],
-                [20.0],
-                [21.0],
-                [22.0],
-                [23.0],
-                [24.0],
-                [25.0],
-                [26.0],
-                [27.0],
-                [28.0],
-                [29.0],
-                [30.0],
-                [31.0],
-                [32.0],
-                [33.0],
-                [34.0],
-                [35.0],
-                [36.0],
-                [37.0],
-                [38.0],
-                [39.0],
-                [40.0],
-                [41.0],
-                [42.0],
-                [43.0],
-                [44.0],
-                [45.0],
-                [46.0],
-                [47.0],
-                [48.0],
-                [49.0],
-                [50.0],
-                [51.0],
-            ],
-            [
-                [52.0],
-                [53.0],
-                [54.0],
-                [55.0],
-                [56.0],
-                [57.0],
-                [58.0],
-                [59.0],
-                [60.0],
-                [61.0],
-            ],
-        );
-
-        assert_eq!(result, expected);
+        let param = (0..62).map(|i| i as f32).collect::<Vec<f32>>();
+        let layout = ParamLayout {
+            trans_dim: 12,
+            shape_dim: 40,
+            exp_dim: 10,
+        };
+
+        let (r, offset, alpha_shp, alpha_exp) = parse_param(&param, &layout);
+
+        assert_eq!(r, [[0.0, 1.0, 2.0], [4.0, 5.0, 6.0], [8.0, 9.0, 10.0]]);
+        assert_eq!(offset, [[3.0], [7.0], [11.0]]);
+        assert_eq!(alpha_shp, &param[12..52]);
+        assert_eq!(alpha_exp, &param[52..62]);
+
+        // Larger BFM variants have more shape and expression coefficients
+        let param = (0..141).map(|i| i as f32).collect::<Vec<f32>>();
+        let layout = ParamLayout {
+            trans_dim: 12,
+            shape_dim: 100,
+            exp_dim: 29,
+        };
+
+        let (_, _, alpha_shp, alpha_exp) = parse_param(&param, &layout);
+
+        assert_eq!(alpha_shp, &param[12..112]);
+        assert_eq!(alpha_exp, &param[112..141]);
     }
 
     #[test]
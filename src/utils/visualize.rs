@@ -4,7 +4,6 @@ use opencv::{
     imgproc::{circle, line, LINE_8},
 };
 
-#[allow(dead_code)]
 pub fn draw_landmark(
     frame: Mat,
     pts_3d: Vec<Vec<f32>>,
@@ -88,3 +87,26 @@ pub fn draw_landmark(
 
     Ok(img)
 }
+
+// Keypoints given by the face detector (eyes, nose, mouth...), in frame coordinates
+pub fn draw_keypoints(
+    frame: Mat,
+    keypoints: &[(f32, f32)],
+    color: (f64, f64, f64),
+    size: i32,
+) -> Result<Mat> {
+    let mut img = frame;
+    for &(x, y) in keypoints {
+        circle(
+            &mut img,
+            Point::new(x.round() as i32, y.round() as i32),
+            size,
+            Scalar::new(color.0, color.1, color.2, 0.),
+            -1,
+            LINE_8,
+            0,
+        )?
+    }
+
+    Ok(img)
+}